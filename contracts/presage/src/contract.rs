@@ -1,24 +1,34 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Binary, CosmosMsg, Deps, DepsMut, Env, Event, MessageInfo,
-    Response, StdResult, WasmMsg, SubMsg, Uint128,
+    from_json, to_json_binary, Addr, Binary, CosmosMsg, Decimal, Deps, DepsMut, Env, Event, MessageInfo,
+    Order, Response, StdResult, Storage, WasmMsg, SubMsg, Uint128,
 };
-use cw20::Cw20ExecuteMsg;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_storage_plus::Bound;
+use std::cmp::Ordering;
 
-use cw2::set_contract_version;
-use pyth_sdk_cw::PriceIdentifier;
+use cw2::{get_contract_version, set_contract_version};
+use sha2::{Digest, Sha256};
 
 use crate::error::ContractError;
 use crate::msg::{
-    ExecuteMsg, InstantiateMsg, QueryMsg, RoundResponse, ConfigResponse, 
-    UserRoundsResponse, ClaimableResponse, RefundableResponse,
+    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg, RoundResponse, ConfigResponse, 
+    UserRoundsResponse, ClaimableResponse, RefundableResponse, BalanceResponse, AutoRollResponse,
+    StandingOrdersResponse, MarketBetResponse, ChallengeResponse, PriceAttestation,
 };
 use crate::state::{
-    Config, Round, Position, BetInfo, ROUNDS, LEDGER, USER_ROUNDS, 
-    CONFIG, CURRENT_EPOCH, PAUSED, TREASURY,
+    Config, Round, Position, BetInfo, AutoRoll, RollStrategy, StandingOrder, CancelWindow, ExposureCaps, MarketKind, StrikeOffset, Market, MarketResolution, SettlementChallenge, Challenge, PriceSource, PriceOrigin, PriceInfo, PricePhase, AttestedKey, FedPrice, ROUNDS, LEDGER, USER_ROUNDS, 
+    CONFIG, CURRENT_EPOCH, PAUSED, SETTLEMENT_HALTED, TREASURY, PENDING_ROUND_FEES, PENDING_MARKET_FEES, BALANCES, AUTO_ROLL, STANDING_ORDERS, OWNER_STANDING_ORDERS,
+    NEXT_ORDER_ID, ORDER_FILL_CURSOR, MARKETS, MARKET_LEDGER, MARKET_VOTES, NEXT_MARKET_ID, CHALLENGES, FED_PRICES,
+    USED_ATTESTATIONS, LEGACY_EPOCH, attested_prices, default_price_window_seconds,
 };
-use crate::oracle::{get_btc_price,get_mock_btc_price};
+use crate::oracle::{
+    attestation_digest, get_twap_price, is_valid_public_key, verify_attestation,
+};
+use crate::pyth_wrapper::{is_valid_feed_id, query_price, query_price_unchecked, query_update_fee, update_price_feeds_msg};
+#[cfg(feature = "mock-oracle")]
+use crate::state::MOCK_PRICE;
 
 
 const CONTRACT_NAME: &str = "crates.io:presage-prediction";
@@ -26,6 +36,30 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 // Oracle constants
 const ORACLE_TIME_LIMIT: u64 = 60; // 60 seconds staleness limit
+const MAX_PRICE_SOURCES: usize = 8;
+
+// Pagination for operator-pushed payouts
+const DEFAULT_PAYOUT_LIMIT: u32 = 30;
+const MAX_PAYOUT_LIMIT: u32 = 100;
+
+// One-off markets
+const MAX_DESCRIPTION_LENGTH: usize = 256;
+const MAX_RESOLVERS: usize = 20;
+
+// Active standing orders one address can hold
+const MAX_STANDING_ORDERS_PER_USER: usize = 10;
+// Standing orders filled when a round starts and per FillStandingOrders call by default. The
+// operator pages FillStandingOrders until it reports done before the round locks; orders not
+// reached by then miss that round and keep their remaining rounds.
+const DEFAULT_FILL_LIMIT: u32 = 20;
+const MAX_FILL_LIMIT: u32 = 50;
+
+// Range rounds keep one pool per bucket
+const MAX_RANGE_BUCKETS: usize = 16;
+
+// Pending fees ClaimTreasury looks at per map, oldest first. Fees normally reach the treasury
+// when claims open; this sweeps those of rounds and markets nobody has claimed from.
+const FEE_RELEASE_LIMIT: usize = 30;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -66,12 +100,27 @@ pub fn instantiate(
         treasury_fee: msg.treasury_fee,
         oracle_address,
         btc_price_feed_id: msg.btc_price_feed_id.clone(), // Clone to fix the moved value error
+        push_payouts_enabled: false,
+        proposer_address: None,
+        market_kind: MarketKind::UpDown,
+        flat_band_bps: None,
+        cancel_window: None,
+        exposure_caps: ExposureCaps::default(),
+        settlement_challenge: None,
+        price_sources: vec![],
+        max_deviation_bps: 0,
+        fallback_price_source: None,
+        price_window_seconds: default_price_window_seconds(),
+        max_price_move_bps: None,
+        guardian_address: None,
     };
 
     CONFIG.save(deps.storage, &config)?;
     CURRENT_EPOCH.save(deps.storage, &0u64)?;
     PAUSED.save(deps.storage, &false)?;
+    SETTLEMENT_HALTED.save(deps.storage, &false)?;
     TREASURY.save(deps.storage, &Uint128::zero())?;
+    LEGACY_EPOCH.save(deps.storage, &0u64)?;
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -82,6 +131,41 @@ pub fn instantiate(
         .add_attribute("btc_price_feed_id", msg.btc_price_feed_id))
 }
 
+// State written by earlier versions loads with defaults for the fields added since. Coming
+// from the first release, every round it started is marked legacy and a fresh round is
+// started, so ExecuteRound never walks back over rounds that release locked but never ended.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    mut deps: DepsMut,
+    env: Env,
+    _msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigration { contract: stored.contract });
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // Write the config back so the defaulted fields are stored explicitly
+    let config = CONFIG.load(deps.storage)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    let mut response = Response::new();
+    if LEGACY_EPOCH.may_load(deps.storage)?.is_none() {
+        let current_epoch = CURRENT_EPOCH.load(deps.storage)?;
+        LEGACY_EPOCH.save(deps.storage, &current_epoch)?;
+        if current_epoch > 0 {
+            response = response.add_events(execute_start_round(&mut deps, env, current_epoch + 1, &config)?.events);
+        }
+        response = response.add_attribute("legacy_epoch", current_epoch.to_string());
+    }
+
+    Ok(response
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -92,12 +176,88 @@ pub fn execute(
     match msg {
         ExecuteMsg::BetBull { epoch, amount } => execute_bet(deps, env, info, epoch, amount, Position::Bull),
         ExecuteMsg::BetBear { epoch, amount } => execute_bet(deps, env, info, epoch, amount, Position::Bear),
+        ExecuteMsg::BetFlat { epoch, amount } => execute_bet(deps, env, info, epoch, amount, Position::Flat),
+        ExecuteMsg::BetCalm { epoch, amount } => execute_bet(deps, env, info, epoch, amount, Position::Calm),
+        ExecuteMsg::BetVolatile { epoch, amount } => execute_bet(deps, env, info, epoch, amount, Position::Volatile),
+        ExecuteMsg::BetBucket { epoch, amount, bucket } =>
+            execute_bet(deps, env, info, epoch, amount, Position::Bucket(bucket)),
+        ExecuteMsg::BetMarket { market_id, position, amount } =>
+            execute_bet_market(deps, env, info, market_id, position, amount),
+        ExecuteMsg::ClaimMarkets { market_ids } => execute_claim_markets(deps, env, info, market_ids),
+        ExecuteMsg::DisputeMarket { market_id } => execute_dispute_market(deps, env, info, market_id),
+        ExecuteMsg::CancelBet { epoch } => execute_cancel_bet(deps, env, info, epoch),
         ExecuteMsg::Claim { epochs } => execute_claim(deps, env, info, epochs),
-        ExecuteMsg::ExecuteRound {} => execute_round(deps, env, info),
+        ExecuteMsg::Deposit {} => execute_deposit(deps, info),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, info, msg),
+        ExecuteMsg::Withdraw { amount } => execute_withdraw(deps, info, amount),
+        ExecuteMsg::SetAutoRoll { position_strategy, fraction } =>
+            execute_set_auto_roll(deps, info, position_strategy, fraction),
+        ExecuteMsg::PlaceStandingOrder { position, amount_per_round, rounds } =>
+            execute_place_standing_order(deps, info, position, amount_per_round, rounds),
+        ExecuteMsg::CancelStandingOrder { id } => execute_cancel_standing_order(deps, info, id),
+        ExecuteMsg::ChallengeSettlement { epoch, close_price, close_price_b, price_update } => {
+            execute_challenge_settlement(deps, env, info, epoch, close_price, close_price_b, price_update)
+        }
+        ExecuteMsg::ResolveChallenge { epoch, upheld } => execute_resolve_challenge(deps, env, info, epoch, upheld),
+        ExecuteMsg::VerifyChallenge { epoch } => execute_verify_challenge(deps, env, info, epoch),
+        ExecuteMsg::SetSettlementChallenge { settlement_challenge } => {
+            execute_set_settlement_challenge(deps, info, settlement_challenge)
+        }
+        ExecuteMsg::SubmitPrice { price_feed_id, price, expo } => {
+            execute_submit_price(deps, env, info, price_feed_id, price, expo)
+        }
+        ExecuteMsg::SetPriceSources { price_sources, max_deviation_bps } => {
+            execute_set_price_sources(deps, info, price_sources, max_deviation_bps)
+        }
+        ExecuteMsg::SetFallbackPriceSource { fallback_price_source } => {
+            execute_set_fallback_price_source(deps, info, fallback_price_source)
+        }
+        ExecuteMsg::SetPriceWindow { price_window_seconds } => execute_set_price_window(deps, info, price_window_seconds),
+        ExecuteMsg::SetCircuitBreaker { max_price_move_bps } => execute_set_circuit_breaker(deps, info, max_price_move_bps),
+        ExecuteMsg::SetGuardian { guardian_address } => execute_set_guardian(deps, info, guardian_address),
+        #[cfg(feature = "mock-oracle")]
+        ExecuteMsg::SetMockPrice { price } => execute_set_mock_price(deps, info, price),
+        ExecuteMsg::ClaimFor { user, epochs } => execute_claim_for(deps, env, info, user, epochs),
+        ExecuteMsg::CreateMarket { description, strike_price, betting_deadline, resolution_time } =>
+            execute_create_market(deps, env, info, description, strike_price, betting_deadline, resolution_time),
+        ExecuteMsg::CreateEventMarket {
+            description,
+            resolvers,
+            quorum,
+            betting_deadline,
+            resolution_time,
+            vote_window_seconds,
+            dispute_window_seconds,
+            dispute_bond,
+        } => execute_create_event_market(
+            deps,
+            env,
+            info,
+            description,
+            resolvers,
+            quorum,
+            betting_deadline,
+            resolution_time,
+            vote_window_seconds,
+            dispute_window_seconds,
+            dispute_bond,
+        ),
+        ExecuteMsg::ResolveMarket { market_id } => execute_resolve_market(deps, env, info, market_id),
+        ExecuteMsg::VoteMarket { market_id, outcome } => execute_vote_market(deps, env, info, market_id, outcome),
+        ExecuteMsg::CancelMarket { market_id } => execute_cancel_market(deps, env, info, market_id),
+        ExecuteMsg::ResolveMarketDispute { market_id, outcome } =>
+            execute_resolve_market_dispute(deps, env, info, market_id, outcome),
+        ExecuteMsg::CancelRound { epoch, reason } => execute_cancel_round(deps, env, info, epoch, reason),
+        ExecuteMsg::PushPayouts { epoch, start_after, limit } =>
+            execute_push_payouts(deps, env, info, epoch, start_after, limit),
+        ExecuteMsg::FillStandingOrders { limit } => execute_fill_standing_orders(deps, env, info, limit),
+        ExecuteMsg::ExecuteRound { attestations } => execute_round(deps, env, info, attestations.unwrap_or_default()),
         ExecuteMsg::GenesisStartRound {} => execute_genesis_start_round(deps, env, info),
-        ExecuteMsg::GenesisLockRound {} => execute_genesis_lock_round(deps, env, info),
+        ExecuteMsg::GenesisLockRound { attestations } =>
+            execute_genesis_lock_round(deps, env, info, attestations.unwrap_or_default()),
         ExecuteMsg::Pause {} => execute_pause(deps, info),
         ExecuteMsg::Unpause {} => execute_unpause(deps, info),
+        ExecuteMsg::ResumeSettlement {} => execute_resume_settlement(deps, info),
         ExecuteMsg::ClaimTreasury {} => execute_claim_treasury(deps, env, info),
         ExecuteMsg::SetBufferAndIntervalSeconds { buffer_seconds, interval_seconds } => 
             execute_set_buffer_and_interval_seconds(deps, info, buffer_seconds, interval_seconds),
@@ -109,6 +269,18 @@ pub fn execute(
             execute_set_treasury_fee(deps, info, treasury_fee),
         ExecuteMsg::SetOracleInfo { oracle_address, btc_price_feed_id } =>
             execute_set_oracle_info(deps, info, oracle_address, btc_price_feed_id),
+        ExecuteMsg::SetPushPayouts { enabled } =>
+            execute_set_push_payouts(deps, info, enabled),
+        ExecuteMsg::SetProposer { proposer_address } =>
+            execute_set_proposer(deps, info, proposer_address),
+        ExecuteMsg::SetMarketKind { market_kind } =>
+            execute_set_market_kind(deps, info, market_kind),
+        ExecuteMsg::SetFlatBand { flat_band_bps } =>
+            execute_set_flat_band(deps, info, flat_band_bps),
+        ExecuteMsg::SetCancelWindow { cancel_window } =>
+            execute_set_cancel_window(deps, info, cancel_window),
+        ExecuteMsg::SetExposureCaps { exposure_caps } =>
+            execute_set_exposure_caps(deps, info, exposure_caps),
     }
 }
 
@@ -126,25 +298,22 @@ fn execute_bet(
     }
 
     let config = CONFIG.load(deps.storage)?;
-    
-    if amount < config.min_bet_amount {
-        return Err(ContractError::BetTooSmall {});
-    }
+    let user_addr = info.sender.clone();
 
-    let mut round = ROUNDS.load(deps.storage, epoch)?;
-    if env.block.time.seconds() >= round.lock_timestamp {
-        return Err(ContractError::RoundNotBettable {});
-    }
+    let stake = place_bet(deps.storage, &env, &config, &user_addr, epoch, amount, position.clone())?;
 
-    let user_addr = info.sender.clone();
-    if LEDGER.has(deps.storage, (epoch, user_addr.clone())) {
-        return Err(ContractError::AlreadyBet {});
-    }
-    let sent_amount = info.funds.iter().find(|c| c.denom == config.usdc_token).map(|c| c.amount).unwrap_or(Uint128::zero());
+    // Bets without attached funds are paid from the internal balance
+    let funding = if info.funds.is_empty() {
+        debit_balance(deps.storage, &user_addr, amount)?;
+        "balance"
+    } else {
+        let sent_amount = info.funds.iter().find(|c| c.denom == config.usdc_token).map(|c| c.amount).unwrap_or(Uint128::zero());
 
-    if sent_amount != amount {
-        return Err(ContractError::InvalidBetFunds {});
-    }
+        if sent_amount != amount {
+            return Err(ContractError::InvalidBetFunds {});
+        }
+        "funds"
+    };
 
     // let transfer_msg = CosmosMsg::Wasm(WasmMsg::Execute {
     //     contract_addr: config.usdc_token.to_string(),
@@ -156,308 +325,2715 @@ fn execute_bet(
     //     funds: vec![],
     // });
 
-    match position {
-        Position::Bull => round.bull_amount += amount,
-        Position::Bear => round.bear_amount += amount,
+    Ok(Response::new()
+        .add_attribute("method", "bet")
+        .add_attribute("position", position_str(&position))
+        .add_attribute("user", info.sender)
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("stake", stake.to_string())
+        .add_attribute("funding", funding))
+}
+
+// Records a bet on the round, or tops up the user's existing bet on the same side,
+// and returns the user's total stake. Every check runs before the first write, so
+// callers that place bets on a best-effort basis can drop the error without partial state.
+fn place_bet(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    user_addr: &Addr,
+    epoch: u64,
+    amount: Uint128,
+    position: Position,
+) -> Result<Uint128, ContractError> {
+    if amount < config.min_bet_amount {
+        return Err(ContractError::BetTooSmall {});
     }
-    round.total_amount += amount;
-    ROUNDS.save(deps.storage, epoch, &round)?;
 
-    let bet_info = BetInfo {
-        position: position.clone(),
-        amount,
-        claimed: false,
+    let mut round = ROUNDS.load(storage, epoch)?;
+    if round.cancelled || env.block.time.seconds() >= round.lock_timestamp {
+        return Err(ContractError::RoundNotBettable {});
+    }
+    if !offered_positions(&round).contains(&position) {
+        return Err(ContractError::PositionNotOffered { epoch });
+    }
+
+    let bet_info = match LEDGER.may_load(storage, (epoch, user_addr.clone()))? {
+        Some(existing) => {
+            if existing.position != position {
+                return Err(ContractError::PositionMismatch {});
+            }
+            BetInfo {
+                amount: existing.amount.checked_add(amount)?,
+                ..existing
+            }
+        }
+        None => BetInfo {
+            position: position.clone(),
+            amount,
+            claimed: false,
+        },
     };
 
+    check_exposure(&config.exposure_caps, &round, &position, amount, bet_info.amount)?;
 
+    *pool_amount_mut(&mut round, &position).ok_or(ContractError::PositionNotOffered { epoch })? += amount;
+    round.total_amount += amount;
+    ROUNDS.save(storage, epoch, &round)?;
 
-    LEDGER.save(deps.storage, (epoch, user_addr.clone()), &bet_info)?;
+    LEDGER.save(storage, (epoch, user_addr.clone()), &bet_info)?;
 
-    let mut user_rounds = USER_ROUNDS.may_load(deps.storage, user_addr.clone())?.unwrap_or_default();
+    let mut user_rounds = USER_ROUNDS.may_load(storage, user_addr.clone())?.unwrap_or_default();
     if !user_rounds.contains(&epoch) {
         user_rounds.push(epoch);
-        USER_ROUNDS.save(deps.storage, user_addr, &user_rounds)?;
+        USER_ROUNDS.save(storage, user_addr.clone(), &user_rounds)?;
     }
 
-    let position_str = match position {
-        Position::Bull => "bull",
-        Position::Bear => "bear",
-    };
-    
-    Ok(Response::new()
-        .add_attribute("method", "bet")
-        .add_attribute("position", position_str)
-        .add_attribute("user", info.sender)
-        .add_attribute("epoch", epoch.to_string())
-        .add_attribute("amount", amount.to_string()))
+    Ok(bet_info.amount)
 }
 
-fn execute_claim(
+// Withdraws a bet before the cancel cutoff. The cancellation fee goes to the treasury
+// and the rest of the stake is credited to the internal balance.
+fn execute_cancel_bet(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    epochs: Vec<u64>,
+    epoch: u64,
 ) -> Result<Response, ContractError> {
-    if epochs.is_empty() {
-        return Err(ContractError::EmptyEpochs {});
+    let paused = PAUSED.load(deps.storage)?;
+    if paused {
+        return Err(ContractError::Paused {});
     }
 
     let config = CONFIG.load(deps.storage)?;
-    let user_addr = info.sender.clone();
-    let mut total_reward = Uint128::zero();
-    let mut events = Vec::new();
+    let cancel_window = config.cancel_window.ok_or(ContractError::CancelDisabled {})?;
 
-    for epoch in epochs.iter() {
-        let round = ROUNDS.load(deps.storage, *epoch)?;
-        if !round.oracle_called {
-            return Err(ContractError::RoundNotEnded { epoch: *epoch });
-        }
+    let mut round = ROUNDS.load(deps.storage, epoch)?;
+    if round.cancelled {
+        return Err(ContractError::RoundCancelled { epoch });
+    }
+    let cutoff = round.lock_timestamp.saturating_sub(cancel_window.cutoff_seconds);
+    if env.block.time.seconds() >= cutoff {
+        return Err(ContractError::CancelWindowClosed { epoch });
+    }
 
-        if !LEDGER.has(deps.storage, (*epoch, user_addr.clone())) {
-            return Err(ContractError::NoBetRecord { epoch: *epoch });
-        }
+    let user_addr = info.sender.clone();
+    let bet_info = LEDGER
+        .may_load(deps.storage, (epoch, user_addr.clone()))?
+        .ok_or(ContractError::NoBetRecord { epoch })?;
 
-        let mut bet_info = LEDGER.load(deps.storage, (*epoch, user_addr.clone()))?;
-        if bet_info.claimed {
-            return Err(ContractError::AlreadyClaimed { epoch: *epoch });
-        }
+    let pool = pool_amount_mut(&mut round, &bet_info.position).ok_or(ContractError::PositionNotOffered { epoch })?;
+    *pool = pool.checked_sub(bet_info.amount)?;
+    round.total_amount = round.total_amount.checked_sub(bet_info.amount)?;
+    ROUNDS.save(deps.storage, epoch, &round)?;
 
-        let reward = calculate_reward(round.clone(), bet_info.clone())?;
-        if reward == Uint128::zero() {
-            return Err(ContractError::NotWinner { epoch: *epoch });
-        }
+    LEDGER.remove(deps.storage, (epoch, user_addr.clone()));
+    let mut user_rounds = USER_ROUNDS.may_load(deps.storage, user_addr.clone())?.unwrap_or_default();
+    user_rounds.retain(|e| *e != epoch);
+    USER_ROUNDS.save(deps.storage, user_addr.clone(), &user_rounds)?;
 
-        bet_info.claimed = true;
-        LEDGER.save(deps.storage, (*epoch, user_addr.clone()), &bet_info)?;
+    let fee = bet_info.amount * Uint128::from(cancel_window.fee_bps) / Uint128::from(10000u32);
+    let refund = bet_info.amount.checked_sub(fee)?;
 
-        total_reward += reward;
+    let mut treasury = TREASURY.load(deps.storage)?;
+    treasury += fee;
+    TREASURY.save(deps.storage, &treasury)?;
 
-        events.push(Event::new("claim")
-            .add_attribute("epoch", epoch.to_string())
-            .add_attribute("user", user_addr.to_string())
-            .add_attribute("reward", reward.to_string()));
-    }
+    let balance = credit_balance(deps.storage, &user_addr, refund)?;
 
-    let transfer_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: config.usdc_token.to_string(),
-        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: user_addr.to_string(),
-            amount: total_reward, 
-        })?,
-        funds: vec![],
-    });
+    let event = Event::new("cancel_bet")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("user", user_addr.to_string())
+        .add_attribute("position", position_str(&bet_info.position))
+        .add_attribute("amount", bet_info.amount.to_string())
+        .add_attribute("fee", fee.to_string())
+        .add_attribute("refund", refund.to_string());
 
     Ok(Response::new()
-        .add_submessage(SubMsg::new(transfer_msg))
-        .add_attribute("method", "claim")
+        .add_event(event)
+        .add_attribute("method", "cancel_bet")
         .add_attribute("user", user_addr)
-        .add_attribute("total_reward", total_reward.to_string())
-        .add_events(events))
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("balance", balance.to_string()))
 }
 
-fn calculate_reward(round: Round, bet_info: BetInfo) -> Result<Uint128, ContractError> {
-    let is_winner = match bet_info.position {
-        Position::Bull => round.close_price > round.lock_price,
-        Position::Bear => round.close_price < round.lock_price,
-    };
+fn check_exposure(
+    caps: &ExposureCaps,
+    round: &Round,
+    position: &Position,
+    amount: Uint128,
+    user_stake: Uint128,
+) -> Result<(), ContractError> {
+    if let Some(max_bet_amount) = caps.max_bet_amount {
+        if amount > max_bet_amount {
+            return Err(ContractError::BetTooLarge {});
+        }
+    }
 
-    if !is_winner || round.close_price == round.lock_price {
-        return Ok(Uint128::zero());
+    if let Some(max_user_round_amount) = caps.max_user_round_amount {
+        if user_stake > max_user_round_amount {
+            return Err(ContractError::UserRoundCapExceeded {});
+        }
     }
 
-    let position_amount = match bet_info.position {
-        Position::Bull => round.bull_amount,
-        Position::Bear => round.bear_amount,
-    };
+    if let Some(max_round_amount) = caps.max_round_amount {
+        if round.total_amount.checked_add(amount)? > max_round_amount {
+            return Err(ContractError::RoundCapExceeded {});
+        }
+    }
 
-    let opposing_amount = match bet_info.position {
-        Position::Bull => round.bear_amount,
-        Position::Bear => round.bull_amount,
+    // The ratio is between the two pools of a Bull / Bear or Calm / Volatile pair and only
+    // applies once the opposing side has liquidity; the first bets on an empty side are always accepted.
+    let sides = match position {
+        Position::Bull => Some((round.bull_amount, round.bear_amount)),
+        Position::Bear => Some((round.bear_amount, round.bull_amount)),
+        Position::Calm => Some((round.calm_amount, round.volatile_amount)),
+        Position::Volatile => Some((round.volatile_amount, round.calm_amount)),
+        Position::Flat | Position::Bucket(_) | Position::Yes | Position::No => None,
     };
+    if let (Some(max_imbalance_ratio), Some((side_amount, opposing_amount))) = (caps.max_imbalance_ratio, sides) {
+        let side_amount = side_amount.checked_add(amount)?;
+        if !opposing_amount.is_zero()
+            && side_amount > opposing_amount.mul_floor(max_imbalance_ratio)
+        {
+            return Err(ContractError::ImbalanceExceeded {});
+        }
+    }
 
-    if position_amount == Uint128::zero() {
-        return Ok(Uint128::zero());
+    Ok(())
+}
+
+fn position_str(position: &Position) -> String {
+    match position {
+        Position::Bull => "bull".to_string(),
+        Position::Bear => "bear".to_string(),
+        Position::Flat => "flat".to_string(),
+        Position::Bucket(bucket) => format!("bucket_{}", bucket),
+        Position::Yes => "yes".to_string(),
+        Position::No => "no".to_string(),
+        Position::Calm => "calm".to_string(),
+        Position::Volatile => "volatile".to_string(),
     }
+}
 
-    let reward_amount = if opposing_amount == Uint128::zero() {
-        bet_info.amount
-    } else {
-        let reward_base = round.total_amount.checked_sub(round.reward_base_amount)?;
-        let reward = reward_base.checked_mul(bet_info.amount)?.checked_div(position_amount)?;
-        reward
-    };
+// The positions a round takes bets on, as fixed by its market kind when it started
+fn offered_positions(round: &Round) -> Vec<Position> {
+    kind_positions(&round.kind, round.flat_band_bps.is_some())
+}
 
-    Ok(reward_amount)
+fn kind_positions(kind: &MarketKind, flat_band: bool) -> Vec<Position> {
+    match kind {
+        MarketKind::UpDown => {
+            let mut positions = vec![Position::Bull, Position::Bear];
+            if flat_band {
+                positions.push(Position::Flat);
+            }
+            positions
+        }
+        MarketKind::Range { boundaries_bps } => (0..=boundaries_bps.len() as u32).map(Position::Bucket).collect(),
+        MarketKind::StrikeOffset { .. } | MarketKind::Relative { .. } => vec![Position::Bull, Position::Bear],
+        MarketKind::Volatility { .. } => vec![Position::Calm, Position::Volatile],
+    }
 }
 
-fn execute_round(
-    mut deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    if info.sender != config.operator_address {
-        return Err(ContractError::Unauthorized {});
+fn pool_amount(round: &Round, position: &Position) -> Uint128 {
+    match position {
+        Position::Bull => round.bull_amount,
+        Position::Bear => round.bear_amount,
+        Position::Flat => round.flat_amount,
+        Position::Bucket(bucket) => round.bucket_amounts.get(*bucket as usize).copied().unwrap_or_default(),
+        Position::Calm => round.calm_amount,
+        Position::Volatile => round.volatile_amount,
+        Position::Yes | Position::No => Uint128::zero(),
+    }
+}
+
+fn pool_amount_mut<'a>(round: &'a mut Round, position: &Position) -> Option<&'a mut Uint128> {
+    match position {
+        Position::Bull => Some(&mut round.bull_amount),
+        Position::Bear => Some(&mut round.bear_amount),
+        Position::Flat => Some(&mut round.flat_amount),
+        Position::Bucket(bucket) => round.bucket_amounts.get_mut(*bucket as usize),
+        Position::Calm => Some(&mut round.calm_amount),
+        Position::Volatile => Some(&mut round.volatile_amount),
+        Position::Yes | Position::No => None,
     }
+}
 
+// Escrows `amount_per_round * rounds` up front; a bet is placed from the escrow each
+// time a new round starts. The position must be one that rounds started now would offer.
+fn execute_place_standing_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    position: Position,
+    amount_per_round: Uint128,
+    rounds: u64,
+) -> Result<Response, ContractError> {
     let paused = PAUSED.load(deps.storage)?;
     if paused {
         return Err(ContractError::Paused {});
     }
 
-    let current_epoch = CURRENT_EPOCH.load(deps.storage)?;
-    if current_epoch == 0 {
-        return Err(ContractError::GenesisNotStarted {});
+    let config = CONFIG.load(deps.storage)?;
+    if rounds == 0 {
+        return Err(ContractError::InvalidStandingOrder {});
+    }
+    if amount_per_round < config.min_bet_amount {
+        return Err(ContractError::BetTooSmall {});
+    }
+    if !kind_positions(&config.market_kind, config.flat_band_bps.is_some()).contains(&position) {
+        return Err(ContractError::InvalidOrderPosition {});
     }
 
-    let current_round = ROUNDS.load(deps.storage, current_epoch)?;
-    let current_timestamp = env.block.time.seconds();
-
-    if current_timestamp >= current_round.close_timestamp {
-        execute_end_round(&mut deps, env.clone(), current_epoch, &config)?;
+    let user_addr = info.sender.clone();
+    let active_orders = OWNER_STANDING_ORDERS
+        .prefix(user_addr.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .count();
+    if active_orders >= MAX_STANDING_ORDERS_PER_USER {
+        return Err(ContractError::TooManyStandingOrders {});
+    }
 
-        let new_epoch = current_epoch + 1;
-        execute_start_round(&mut deps, env.clone(), new_epoch, &config)?;
+    let escrow = amount_per_round.checked_mul(Uint128::from(rounds))?;
 
-        let response = execute_lock_round(&mut deps, env, new_epoch, &config)?;
-        
-        return Ok(response);
-    } else if current_timestamp >= current_round.lock_timestamp {
-        execute_lock_round(&mut deps, env.clone(), current_epoch, &config)?;
+    // Orders without attached funds are paid from the internal balance
+    if info.funds.is_empty() {
+        debit_balance(deps.storage, &user_addr, escrow)?;
+    } else {
+        let sent_amount = info.funds.iter().find(|c| c.denom == config.usdc_token).map(|c| c.amount).unwrap_or(Uint128::zero());
 
-        let response = execute_start_round(&mut deps, env, current_epoch + 1, &config)?;
-        
-        return Ok(response);
+        if sent_amount != escrow {
+            return Err(ContractError::InvalidBetFunds {});
+        }
     }
 
+    let id = NEXT_ORDER_ID.may_load(deps.storage)?.unwrap_or_default() + 1;
+    NEXT_ORDER_ID.save(deps.storage, &id)?;
+
+    let order = StandingOrder {
+        id,
+        owner: user_addr.clone(),
+        position: position.clone(),
+        amount_per_round,
+        rounds_remaining: rounds,
+        escrow,
+    };
+    STANDING_ORDERS.save(deps.storage, id, &order)?;
+    OWNER_STANDING_ORDERS.save(deps.storage, (user_addr.clone(), id), &true)?;
+
     Ok(Response::new()
-        .add_attribute("method", "execute_round")
-        .add_attribute("action", "no_action_needed")
-        .add_attribute("current_epoch", current_epoch.to_string())
-        .add_attribute("current_timestamp", current_timestamp.to_string()))
+        .add_attribute("method", "place_standing_order")
+        .add_attribute("id", id.to_string())
+        .add_attribute("user", user_addr)
+        .add_attribute("position", position_str(&position))
+        .add_attribute("amount_per_round", amount_per_round.to_string())
+        .add_attribute("rounds", rounds.to_string()))
 }
 
-fn execute_start_round(
-    deps: &mut DepsMut,
-    env: Env,
-    epoch: u64,
-    config: &Config,
+fn execute_cancel_standing_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
 ) -> Result<Response, ContractError> {
-    let start_timestamp = env.block.time.seconds();
-    let lock_timestamp = start_timestamp + config.interval_seconds - config.buffer_seconds;
-    let close_timestamp = start_timestamp + config.interval_seconds;
-
-    let new_round = Round {
-        epoch,
-        start_timestamp,
-        lock_timestamp,
-        close_timestamp,
-        lock_price: 0,
-        close_price: 0,
-        total_amount: Uint128::zero(),
-        bull_amount: Uint128::zero(),
-        bear_amount: Uint128::zero(),
-        reward_base_amount: Uint128::zero(),
-        reward_amount: Uint128::zero(),
-        oracle_called: false,
-    };
-
-    ROUNDS.save(deps.storage, epoch, &new_round)?;
-    CURRENT_EPOCH.save(deps.storage, &epoch)?;
+    let order = STANDING_ORDERS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::StandingOrderNotFound { id })?;
+    if info.sender != order.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    let event = Event::new("start_round")
-        .add_attribute("epoch", epoch.to_string())
-        .add_attribute("start_timestamp", start_timestamp.to_string())
-        .add_attribute("lock_timestamp", lock_timestamp.to_string())
-        .add_attribute("close_timestamp", close_timestamp.to_string());
+    remove_standing_order(deps.storage, &order);
+    let balance = credit_balance(deps.storage, &order.owner, order.escrow)?;
 
     Ok(Response::new()
-        .add_event(event)
-        .add_attribute("method", "start_round")
-        .add_attribute("epoch", epoch.to_string()))
+        .add_attribute("method", "cancel_standing_order")
+        .add_attribute("id", id.to_string())
+        .add_attribute("user", order.owner)
+        .add_attribute("refund", order.escrow.to_string())
+        .add_attribute("balance", balance.to_string()))
 }
 
-fn execute_lock_round(
-    deps: &mut DepsMut,
-    env: Env,
+// Fills up to `limit` standing orders into `epoch` after the cursor. An order that can't
+// be filled still uses up the round: its amount goes back to the owner's balance.
+// Returns the events and whether every order has been visited for this epoch.
+fn fill_standing_orders(
+    storage: &mut dyn Storage,
+    env: &Env,
     epoch: u64,
     config: &Config,
-) -> Result<Response, ContractError> {
-    // Get current BTC price from Pyth oracle
-    let oracle_addr = config.oracle_address.to_string();
-    let price_feed_id = config.btc_price_feed_id.clone();
-    /* 
-    let lock_price = get_btc_price(
-        deps.as_ref(),
-        env.clone(),
-        oracle_addr,
-        &price_feed_id,
-        ORACLE_TIME_LIMIT,
-    )?;  */
+    limit: u32,
+) -> Result<(Vec<Event>, bool), ContractError> {
+    let start_after = match ORDER_FILL_CURSOR.may_load(storage)? {
+        Some((cursor_epoch, last_id)) if cursor_epoch == epoch => Some(Bound::exclusive(last_id)),
+        _ => None,
+    };
+    let orders = STANDING_ORDERS
+        .range(storage, start_after, None, Order::Ascending)
+        .take(limit as usize)
+        .map(|item| item.map(|(_, order)| order))
+        .collect::<StdResult<Vec<StandingOrder>>>()?;
+    let done = orders.len() < limit as usize;
 
-    let lock_price = get_mock_btc_price();
+    let mut events = Vec::new();
+    for mut order in orders {
+        ORDER_FILL_CURSOR.save(storage, &(epoch, order.id))?;
 
+        let amount = order.amount_per_round;
+        let filled = place_bet(storage, env, config, &order.owner, epoch, amount, order.position.clone()).is_ok();
+        if !filled {
+            credit_balance(storage, &order.owner, amount)?;
+        }
 
-    let mut round = ROUNDS.load(deps.storage, epoch)?;
-    round.lock_price = lock_price;
-    
-    let treasury_fee = round.total_amount * Uint128::from(config.treasury_fee) / Uint128::from(10000u32);
-    round.reward_base_amount = treasury_fee;
-    
-    let mut treasury = TREASURY.load(deps.storage)?;
-    treasury += treasury_fee;
-    TREASURY.save(deps.storage, &treasury)?;
-    
-    ROUNDS.save(deps.storage, epoch, &round)?;
+        order.escrow = order.escrow.checked_sub(amount)?;
+        order.rounds_remaining -= 1;
+        if order.rounds_remaining == 0 {
+            remove_standing_order(storage, &order);
+        } else {
+            STANDING_ORDERS.save(storage, order.id, &order)?;
+        }
 
-    let event = Event::new("lock_round")
-        .add_attribute("epoch", epoch.to_string())
-        .add_attribute("lock_timestamp", env.block.time.seconds().to_string())
-        .add_attribute("lock_price", lock_price.to_string());
+        let event_type = if filled { "standing_order_bet" } else { "standing_order_skipped" };
+        events.push(Event::new(event_type)
+            .add_attribute("id", order.id.to_string())
+            .add_attribute("epoch", epoch.to_string())
+            .add_attribute("user", order.owner.to_string())
+            .add_attribute("position", position_str(&order.position))
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("rounds_remaining", order.rounds_remaining.to_string()));
+    }
 
-    Ok(Response::new()
-        .add_event(event)
-        .add_attribute("method", "lock_round")
-        .add_attribute("epoch", epoch.to_string()))
+    Ok((events, done))
 }
 
-fn execute_end_round(
-    deps: &mut DepsMut,
+// Continues filling standing orders into the current round where ExecuteRound stopped
+// Fills the next page of standing orders into the open round. Round start only fills the first
+// page, so the operator calls this until `done` is true, before the round's lock_timestamp.
+fn execute_fill_standing_orders(
+    deps: DepsMut,
     env: Env,
-    epoch: u64,
-    config: &Config,
+    info: MessageInfo,
+    limit: Option<u32>,
 ) -> Result<Response, ContractError> {
-    // Get current BTC price from Pyth oracle
-    let oracle_addr = config.oracle_address.to_string();
-    let price_feed_id = config.btc_price_feed_id.clone();
-    /* 
-    let close_price = get_btc_price(
-        deps.as_ref(),
-        env.clone(),
-        oracle_addr,
-        &price_feed_id,
-        ORACLE_TIME_LIMIT,
-    )?;
-    */
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.operator_address {
+        return Err(ContractError::Unauthorized {});
+    }
+    let paused = PAUSED.load(deps.storage)?;
+    if paused {
+        return Err(ContractError::Paused {});
+    }
+
+    let epoch = CURRENT_EPOCH.load(deps.storage)?;
+    let round = ROUNDS.load(deps.storage, epoch)?;
+    if round.cancelled || env.block.time.seconds() >= round.lock_timestamp {
+        return Err(ContractError::RoundNotBettable {});
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_FILL_LIMIT).min(MAX_FILL_LIMIT);
+    let (events, done) = fill_standing_orders(deps.storage, &env, epoch, &config, limit)?;
+
+    Ok(Response::new()
+        .add_events(events)
+        .add_attribute("method", "fill_standing_orders")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("done", done.to_string()))
+}
+
+fn remove_standing_order(storage: &mut dyn Storage, order: &StandingOrder) {
+    STANDING_ORDERS.remove(storage, order.id);
+    OWNER_STANDING_ORDERS.remove(storage, (order.owner.clone(), order.id));
+}
+
+// One-off market on whether the price settles above a fixed strike at resolution_time.
+// Yes / No pools follow the same parimutuel payout and treasury fee as rounds.
+fn execute_create_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    description: String,
+    strike_price: Uint128,
+    betting_deadline: u64,
+    resolution_time: u64,
+) -> Result<Response, ContractError> {
+    if strike_price.is_zero() {
+        return Err(ContractError::InvalidMarket {});
+    }
+    let strike_price = i128::try_from(strike_price.u128()).map_err(|_| ContractError::InvalidMarket {})?;
+
+    let resolution = MarketResolution::PriceAbove { strike_price };
+    create_market(deps, env, info, description, resolution, betting_deadline, resolution_time)
+}
+
+// Binary event market without a price feed. After resolution_time the resolvers vote
+// Yes or No; once `quorum` of them agree, claims open after the dispute window, during
+// which any bettor can post `dispute_bond` to dispute the outcome to the admin.
+#[allow(clippy::too_many_arguments)]
+fn execute_create_event_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    description: String,
+    resolvers: Vec<String>,
+    quorum: u32,
+    betting_deadline: u64,
+    resolution_time: u64,
+    vote_window_seconds: u64,
+    dispute_window_seconds: u64,
+    dispute_bond: Uint128,
+) -> Result<Response, ContractError> {
+    let mut resolver_addrs = resolvers
+        .iter()
+        .map(|addr| deps.api.addr_validate(addr))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    resolver_addrs.sort();
+    resolver_addrs.dedup();
+
+    // The first outcome to reach quorum resolves the market, so a quorum below a majority
+    // can't resolve it twice
+    let resolver_count = resolver_addrs.len() as u32;
+    if resolver_addrs.is_empty() || resolver_addrs.len() > MAX_RESOLVERS {
+        return Err(ContractError::InvalidMarket {});
+    }
+    if quorum == 0 || quorum > resolver_count || vote_window_seconds == 0 {
+        return Err(ContractError::InvalidMarket {});
+    }
+    // A free dispute would let any losing bettor hold claims for everyone
+    if dispute_window_seconds > 0 && dispute_bond.is_zero() {
+        return Err(ContractError::InvalidMarket {});
+    }
+
+    let resolution = MarketResolution::Committee {
+        resolvers: resolver_addrs,
+        quorum,
+        vote_window_seconds,
+        dispute_window_seconds,
+        dispute_bond,
+    };
+    create_market(deps, env, info, description, resolution, betting_deadline, resolution_time)
+}
+
+fn create_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    description: String,
+    resolution: MarketResolution,
+    betting_deadline: u64,
+    resolution_time: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address && Some(&info.sender) != config.proposer_address.as_ref() {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if description.len() > MAX_DESCRIPTION_LENGTH {
+        return Err(ContractError::InvalidMarket {});
+    }
+    if betting_deadline <= env.block.time.seconds() || resolution_time < betting_deadline {
+        return Err(ContractError::InvalidMarket {});
+    }
+
+    let market_id = NEXT_MARKET_ID.may_load(deps.storage)?.unwrap_or_default() + 1;
+    NEXT_MARKET_ID.save(deps.storage, &market_id)?;
+
+    let mut event = Event::new("create_market")
+        .add_attribute("market_id", market_id.to_string())
+        .add_attribute("creator", info.sender.to_string())
+        .add_attribute("betting_deadline", betting_deadline.to_string())
+        .add_attribute("resolution_time", resolution_time.to_string());
+    event = match &resolution {
+        MarketResolution::PriceAbove { strike_price } => event
+            .add_attribute("resolution", "price_above")
+            .add_attribute("strike_price", strike_price.to_string()),
+        MarketResolution::Committee { resolvers, quorum, .. } => event
+            .add_attribute("resolution", "committee")
+            .add_attribute("resolvers", resolvers.len().to_string())
+            .add_attribute("quorum", quorum.to_string()),
+    };
+
+    let market = Market {
+        id: market_id,
+        creator: info.sender,
+        description,
+        resolution,
+        betting_deadline,
+        resolution_time,
+        settlement_price: None,
+        outcome: None,
+        claims_open_at: None,
+        cancelled: false,
+        disputed_by: None,
+        total_amount: Uint128::zero(),
+        yes_amount: Uint128::zero(),
+        no_amount: Uint128::zero(),
+        reward_base_amount: Uint128::zero(),
+        reward_amount: Uint128::zero(),
+    };
+    MARKETS.save(deps.storage, market_id, &market)?;
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("method", "create_market")
+        .add_attribute("market_id", market_id.to_string()))
+}
+
+fn execute_bet_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_id: u64,
+    position: Position,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let paused = PAUSED.load(deps.storage)?;
+    if paused {
+        return Err(ContractError::Paused {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    if amount < config.min_bet_amount {
+        return Err(ContractError::BetTooSmall {});
+    }
+
+    let mut market = MARKETS.load(deps.storage, market_id)?;
+    if market.cancelled || env.block.time.seconds() >= market.betting_deadline {
+        return Err(ContractError::MarketNotBettable { market_id });
+    }
+
+    let user_addr = info.sender.clone();
+    let bet_info = match MARKET_LEDGER.may_load(deps.storage, (market_id, user_addr.clone()))? {
+        Some(existing) => {
+            if existing.position != position {
+                return Err(ContractError::PositionMismatch {});
+            }
+            BetInfo {
+                amount: existing.amount.checked_add(amount)?,
+                ..existing
+            }
+        }
+        None => BetInfo {
+            position: position.clone(),
+            amount,
+            claimed: false,
+        },
+    };
+
+    match position {
+        Position::Yes => market.yes_amount += amount,
+        Position::No => market.no_amount += amount,
+        _ => return Err(ContractError::InvalidMarketPosition {}),
+    }
+    market.total_amount += amount;
+    MARKETS.save(deps.storage, market_id, &market)?;
+    MARKET_LEDGER.save(deps.storage, (market_id, user_addr.clone()), &bet_info)?;
+
+    // Bets without attached funds are paid from the internal balance
+    let funding = if info.funds.is_empty() {
+        debit_balance(deps.storage, &user_addr, amount)?;
+        "balance"
+    } else {
+        let sent_amount = info.funds.iter().find(|c| c.denom == config.usdc_token).map(|c| c.amount).unwrap_or(Uint128::zero());
+
+        if sent_amount != amount {
+            return Err(ContractError::InvalidBetFunds {});
+        }
+        "funds"
+    };
+
+    Ok(Response::new()
+        .add_attribute("method", "bet_market")
+        .add_attribute("position", position_str(&position))
+        .add_attribute("user", user_addr)
+        .add_attribute("market_id", market_id.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("stake", bet_info.amount.to_string())
+        .add_attribute("funding", funding))
+}
+
+fn execute_resolve_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.operator_address && info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let paused = PAUSED.load(deps.storage)?;
+    if paused {
+        return Err(ContractError::Paused {});
+    }
+    if settlement_halted(deps.storage)? {
+        return Err(ContractError::SettlementHalted {});
+    }
+
+    let mut market = MARKETS.load(deps.storage, market_id)?;
+    let strike_price = match market.resolution {
+        MarketResolution::PriceAbove { strike_price } => strike_price,
+        MarketResolution::Committee { .. } => return Err(ContractError::WrongResolution { market_id }),
+    };
+    if market.cancelled {
+        return Err(ContractError::MarketCancelled { market_id });
+    }
+    if market.outcome.is_some() {
+        return Err(ContractError::MarketAlreadyResolved { market_id });
+    }
+    if env.block.time.seconds() < market.resolution_time {
+        return Err(ContractError::MarketNotResolvable { market_id });
+    }
+
+    // The reading must be published within the price window of the resolution time. Once
+    // the window has passed without one, no later reading can qualify and the market is
+    // cancelled so every stake is refunded.
+    let reading = fetch_price(deps.as_ref(), &env, &config, &config.btc_price_feed_id, None)
+        .and_then(|(info, origin)| check_price_window(&config, &info, market.resolution_time).map(|_| (info, origin)));
+    let window_closed = env.block.time.seconds() > market.resolution_time + config.price_window_seconds;
+    let (price_info, price_origin) = match reading {
+        Ok(reading) => reading,
+        Err(err) if !window_closed => return Err(err),
+        Err(err) => {
+            market.cancelled = true;
+            MARKETS.save(deps.storage, market_id, &market)?;
+
+            let event = Event::new("cancel_market")
+                .add_attribute("market_id", market_id.to_string())
+                .add_attribute("cancelled_by", info.sender.to_string())
+                .add_attribute("reason", err.to_string());
+
+            return Ok(Response::new()
+                .add_event(event)
+                .add_attribute("method", "resolve_market")
+                .add_attribute("market_id", market_id.to_string()));
+        }
+    };
+
+    // Yes wins when the settlement price is strictly above the strike
+    let settlement_price = price_info.price;
+    let outcome = if settlement_price > strike_price { Position::Yes } else { Position::No };
+
+    market.settlement_price = Some(settlement_price);
+    market.outcome = Some(outcome.clone());
+    market.claims_open_at = Some(env.block.time.seconds());
+    take_market_fee(deps.storage, &config, &mut market)?;
+    release_market_fee(deps.storage, market_id)?;
+    MARKETS.save(deps.storage, market_id, &market)?;
+
+    let event = Event::new("resolve_market")
+        .add_attribute("market_id", market_id.to_string())
+        .add_attribute("settlement_price", settlement_price.to_string())
+        .add_attribute("price_origin", price_origin_str(&price_origin))
+        .add_attribute("outcome", position_str(&outcome));
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("method", "resolve_market")
+        .add_attribute("market_id", market_id.to_string()))
+}
+
+fn execute_vote_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_id: u64,
+    outcome: Position,
+) -> Result<Response, ContractError> {
+    let mut market = MARKETS.load(deps.storage, market_id)?;
+    let (quorum, vote_window_seconds, dispute_window_seconds) = match &market.resolution {
+        MarketResolution::Committee { resolvers, quorum, vote_window_seconds, dispute_window_seconds, .. } => {
+            if !resolvers.contains(&info.sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+            (*quorum, *vote_window_seconds, *dispute_window_seconds)
+        }
+        MarketResolution::PriceAbove { .. } => return Err(ContractError::WrongResolution { market_id }),
+    };
+
+    if market.cancelled {
+        return Err(ContractError::MarketCancelled { market_id });
+    }
+    if market.outcome.is_some() {
+        return Err(ContractError::MarketAlreadyResolved { market_id });
+    }
+
+    let now = env.block.time.seconds();
+    if now < market.resolution_time {
+        return Err(ContractError::MarketNotResolvable { market_id });
+    }
+    if now >= market.resolution_time + vote_window_seconds {
+        return Err(ContractError::VotingClosed { market_id });
+    }
+    if outcome != Position::Yes && outcome != Position::No {
+        return Err(ContractError::InvalidMarketPosition {});
+    }
+    if MARKET_VOTES.has(deps.storage, (market_id, info.sender.clone())) {
+        return Err(ContractError::AlreadyVoted { market_id });
+    }
+
+    MARKET_VOTES.save(deps.storage, (market_id, info.sender.clone()), &outcome)?;
+    let votes = MARKET_VOTES
+        .prefix(market_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|vote| matches!(vote, Ok((_, position)) if *position == outcome))
+        .count() as u32;
+
+    let mut response = Response::new()
+        .add_event(Event::new("market_vote")
+            .add_attribute("market_id", market_id.to_string())
+            .add_attribute("resolver", info.sender.to_string())
+            .add_attribute("outcome", position_str(&outcome))
+            .add_attribute("votes", votes.to_string()))
+        .add_attribute("method", "vote_market")
+        .add_attribute("market_id", market_id.to_string());
+
+    if votes >= quorum {
+        let config = CONFIG.load(deps.storage)?;
+        let claims_open_at = now + dispute_window_seconds;
+
+        market.outcome = Some(outcome.clone());
+        market.claims_open_at = Some(claims_open_at);
+        take_market_fee(deps.storage, &config, &mut market)?;
+        if dispute_window_seconds == 0 {
+            release_market_fee(deps.storage, market_id)?;
+        }
+
+        response = response.add_event(Event::new("resolve_market")
+            .add_attribute("market_id", market_id.to_string())
+            .add_attribute("outcome", position_str(&outcome))
+            .add_attribute("claims_open_at", claims_open_at.to_string()));
+    }
+    MARKETS.save(deps.storage, market_id, &market)?;
+
+    Ok(response)
+}
+
+// The admin can cancel a market until its claims open, which covers the dispute
+// window and an open dispute. Anyone can cancel a committee market whose resolvers
+// missed quorum.
+fn execute_cancel_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut market = MARKETS.load(deps.storage, market_id)?;
+    if market.cancelled {
+        return Err(ContractError::MarketCancelled { market_id });
+    }
+
+    let now = env.block.time.seconds();
+    if market_claims_open(&env, &market) {
+        return Err(ContractError::MarketClaimsOpen { market_id });
+    }
+
+    let quorum_missed = match &market.resolution {
+        MarketResolution::Committee { vote_window_seconds, .. } => {
+            market.outcome.is_none() && now >= market.resolution_time + vote_window_seconds
+        }
+        MarketResolution::PriceAbove { .. } => false,
+    };
+    if info.sender != config.admin_address && !quorum_missed {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Drop the fee held back at resolution so every stake can be refunded
+    if !market.reward_base_amount.is_zero() {
+        PENDING_MARKET_FEES.remove(deps.storage, market_id);
+        market.reward_base_amount = Uint128::zero();
+        market.reward_amount = Uint128::zero();
+    }
+    // Cancelling overturns a disputed outcome, so the disputer gets the bond back
+    if let Some(disputed_by) = market.disputed_by.take() {
+        credit_balance(deps.storage, &disputed_by, market_dispute_bond(&market))?;
+    }
+    market.cancelled = true;
+    MARKETS.save(deps.storage, market_id, &market)?;
+
+    let event = Event::new("cancel_market")
+        .add_attribute("market_id", market_id.to_string())
+        .add_attribute("cancelled_by", info.sender.to_string())
+        .add_attribute("quorum_missed", quorum_missed.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("method", "cancel_market")
+        .add_attribute("market_id", market_id.to_string()))
+}
+
+// A resolved market pays out once its dispute window has passed and no dispute is open
+fn market_claims_open(env: &Env, market: &Market) -> bool {
+    market.disputed_by.is_none()
+        && market.claims_open_at.is_some_and(|claims_open_at| env.block.time.seconds() >= claims_open_at)
+}
+
+fn market_dispute_bond(market: &Market) -> Uint128 {
+    match &market.resolution {
+        MarketResolution::Committee { dispute_bond, .. } => *dispute_bond,
+        MarketResolution::PriceAbove { .. } => Uint128::zero(),
+    }
+}
+
+// A bettor disputes a committee outcome during the dispute window, holding claims until
+// the admin rules with ResolveMarketDispute or cancels the market. The market's dispute
+// bond comes from the attached funds or the internal balance.
+fn execute_dispute_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_id: u64,
+) -> Result<Response, ContractError> {
+    let mut market = MARKETS.load(deps.storage, market_id)?;
+    if market.cancelled {
+        return Err(ContractError::MarketCancelled { market_id });
+    }
+    let claims_open_at = market.claims_open_at.ok_or(ContractError::MarketNotResolved { market_id })?;
+    if market.disputed_by.is_some() {
+        return Err(ContractError::MarketAlreadyDisputed { market_id });
+    }
+    if env.block.time.seconds() >= claims_open_at {
+        return Err(ContractError::MarketClaimsOpen { market_id });
+    }
+    if !MARKET_LEDGER.has(deps.storage, (market_id, info.sender.clone())) {
+        return Err(ContractError::NoMarketBet { market_id });
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let bond = market_dispute_bond(&market);
+    let funding = if info.funds.is_empty() {
+        debit_balance(deps.storage, &info.sender, bond)?;
+        "balance"
+    } else {
+        let sent_amount = info.funds.iter().find(|c| c.denom == config.usdc_token).map(|c| c.amount).unwrap_or(Uint128::zero());
+        if sent_amount != bond {
+            return Err(ContractError::InvalidBetFunds {});
+        }
+        "funds"
+    };
+
+    market.disputed_by = Some(info.sender.clone());
+    MARKETS.save(deps.storage, market_id, &market)?;
+
+    let event = Event::new("dispute_market")
+        .add_attribute("market_id", market_id.to_string())
+        .add_attribute("disputed_by", info.sender.to_string())
+        .add_attribute("bond", bond.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("method", "dispute_market")
+        .add_attribute("market_id", market_id.to_string())
+        .add_attribute("funding", funding))
+}
+
+// Settles a dispute on the admin's outcome and opens claims at once. The disputer gets the
+// bond back if the outcome changes; otherwise it goes to the treasury.
+fn execute_resolve_market_dispute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_id: u64,
+    outcome: Position,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+    if outcome != Position::Yes && outcome != Position::No {
+        return Err(ContractError::InvalidMarketPosition {});
+    }
+
+    let mut market = MARKETS.load(deps.storage, market_id)?;
+    if market.cancelled {
+        return Err(ContractError::MarketCancelled { market_id });
+    }
+    let disputed_by = market.disputed_by.take().ok_or(ContractError::MarketNotDisputed { market_id })?;
+
+    let bond = market_dispute_bond(&market);
+    let overturned = market.outcome.as_ref() != Some(&outcome);
+    if overturned {
+        credit_balance(deps.storage, &disputed_by, bond)?;
+    } else {
+        let treasury = TREASURY.load(deps.storage)?.checked_add(bond)?;
+        TREASURY.save(deps.storage, &treasury)?;
+    }
+
+    PENDING_MARKET_FEES.remove(deps.storage, market_id);
+    market.reward_base_amount = Uint128::zero();
+    market.reward_amount = Uint128::zero();
+    market.outcome = Some(outcome.clone());
+    market.claims_open_at = Some(env.block.time.seconds());
+    take_market_fee(deps.storage, &config, &mut market)?;
+    release_market_fee(deps.storage, market_id)?;
+    MARKETS.save(deps.storage, market_id, &market)?;
+
+    let event = Event::new("resolve_market_dispute")
+        .add_attribute("market_id", market_id.to_string())
+        .add_attribute("disputed_by", disputed_by.to_string())
+        .add_attribute("outcome", position_str(&outcome))
+        .add_attribute("overturned", overturned.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("method", "resolve_market_dispute")
+        .add_attribute("market_id", market_id.to_string()))
+}
+
+// Credits winnings, or the full stake of a refunded market, to the internal balance
+fn execute_claim_markets(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    if market_ids.is_empty() {
+        return Err(ContractError::EmptyEpochs {});
+    }
+
+    let user_addr = info.sender.clone();
+    let mut total_reward = Uint128::zero();
+    let mut events = Vec::new();
+
+    for market_id in market_ids.iter() {
+        let market = MARKETS.load(deps.storage, *market_id)?;
+        if !market.cancelled && !market_claims_open(&env, &market) {
+            if market.claims_open_at.is_none() {
+                return Err(ContractError::MarketNotResolved { market_id: *market_id });
+            }
+            return Err(ContractError::MarketInDispute { market_id: *market_id });
+        }
+        release_market_fee(deps.storage, *market_id)?;
+
+        let mut bet_info = MARKET_LEDGER
+            .may_load(deps.storage, (*market_id, user_addr.clone()))?
+            .ok_or(ContractError::NoMarketBet { market_id: *market_id })?;
+        if bet_info.claimed {
+            return Err(ContractError::MarketAlreadyClaimed { market_id: *market_id });
+        }
+
+        let refund = is_refund_market(&market);
+        let reward = if refund {
+            bet_info.amount
+        } else if market.outcome == Some(bet_info.position.clone()) {
+            let winning_amount = market_pool_amount(&market, &bet_info.position);
+            parimutuel_reward(market.total_amount, market.reward_base_amount, winning_amount, bet_info.amount)?
+        } else {
+            Uint128::zero()
+        };
+        if reward == Uint128::zero() {
+            return Err(ContractError::NotMarketWinner { market_id: *market_id });
+        }
+
+        bet_info.claimed = true;
+        MARKET_LEDGER.save(deps.storage, (*market_id, user_addr.clone()), &bet_info)?;
+
+        total_reward += reward;
+        events.push(Event::new(if refund { "market_refund" } else { "market_claim" })
+            .add_attribute("market_id", market_id.to_string())
+            .add_attribute("user", user_addr.to_string())
+            .add_attribute("reward", reward.to_string()));
+    }
+
+    let balance = credit_balance(deps.storage, &user_addr, total_reward)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "claim_markets")
+        .add_attribute("user", user_addr)
+        .add_attribute("total_reward", total_reward.to_string())
+        .add_attribute("balance", balance.to_string())
+        .add_events(events))
+}
+
+// The treasury fee is only taken from markets that pay out; refunded markets return every stake
+fn take_market_fee(
+    storage: &mut dyn Storage,
+    config: &Config,
+    market: &mut Market,
+) -> Result<(), ContractError> {
+    if is_refund_market(market) {
+        return Ok(());
+    }
+
+    let treasury_fee = market.total_amount * Uint128::from(config.treasury_fee) / Uint128::from(10000u32);
+    market.reward_base_amount = treasury_fee;
+    market.reward_amount = market.total_amount.checked_sub(treasury_fee)?;
+    PENDING_MARKET_FEES.save(storage, market.id, &treasury_fee)?;
+
+    Ok(())
+}
+
+fn market_pool_amount(market: &Market, position: &Position) -> Uint128 {
+    match position {
+        Position::Yes => market.yes_amount,
+        Position::No => market.no_amount,
+        _ => Uint128::zero(),
+    }
+}
+
+fn is_refund_market(market: &Market) -> bool {
+    if market.cancelled {
+        return true;
+    }
+
+    match &market.outcome {
+        Some(position) => is_one_sided(market_pool_amount(market, position), market.total_amount),
+        None => false,
+    }
+}
+
+fn execute_deposit(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let amount = info.funds.iter().find(|c| c.denom == config.usdc_token).map(|c| c.amount).unwrap_or(Uint128::zero());
+    if amount == Uint128::zero() || info.funds.len() != 1 {
+        return Err(ContractError::InvalidBetFunds {});
+    }
+
+    deposit(deps, info.sender, amount)
+}
+
+fn execute_receive(
+    deps: DepsMut,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.usdc_token {
+        return Err(ContractError::InvalidBetFunds {});
+    }
+    if wrapper.amount == Uint128::zero() {
+        return Err(ContractError::InvalidBetFunds {});
+    }
+
+    let user_addr = deps.api.addr_validate(&wrapper.sender)?;
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::Deposit {} => deposit(deps, user_addr, wrapper.amount),
+    }
+}
+
+fn deposit(
+    deps: DepsMut,
+    user_addr: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let paused = PAUSED.load(deps.storage)?;
+    if paused {
+        return Err(ContractError::Paused {});
+    }
+
+    let balance = credit_balance(deps.storage, &user_addr, amount)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "deposit")
+        .add_attribute("user", user_addr)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("balance", balance.to_string()))
+}
+
+fn execute_withdraw(
+    deps: DepsMut,
+    info: MessageInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::InsufficientBalance {});
+    }
+
+    let balance = debit_balance(deps.storage, &info.sender, amount)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::new(transfer_msg(&config, &info.sender, amount)?))
+        .add_attribute("method", "withdraw")
+        .add_attribute("user", info.sender)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("balance", balance.to_string()))
+}
+
+fn credit_balance(
+    storage: &mut dyn Storage,
+    user_addr: &Addr,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    let balance = BALANCES.may_load(storage, user_addr.clone())?.unwrap_or_default().checked_add(amount)?;
+    BALANCES.save(storage, user_addr.clone(), &balance)?;
+    Ok(balance)
+}
+
+fn debit_balance(
+    storage: &mut dyn Storage,
+    user_addr: &Addr,
+    amount: Uint128,
+) -> Result<Uint128, ContractError> {
+    let balance = BALANCES.may_load(storage, user_addr.clone())?.unwrap_or_default();
+    if balance < amount {
+        return Err(ContractError::InsufficientBalance {});
+    }
+
+    let balance = balance - amount;
+    if balance.is_zero() {
+        BALANCES.remove(storage, user_addr.clone());
+    } else {
+        BALANCES.save(storage, user_addr.clone(), &balance)?;
+    }
+    Ok(balance)
+}
+
+fn execute_claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    epochs: Vec<u64>,
+) -> Result<Response, ContractError> {
+    let user_addr = info.sender.clone();
+    let (claimed, balance) = claim_to_balance(deps.storage, &env, &user_addr, &epochs)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "claim")
+        .add_attribute("user", user_addr)
+        .add_attribute("total_reward", claimed.total_reward.to_string())
+        .add_attribute("balance", balance.to_string())
+        .add_events(claimed.events))
+}
+
+// Rewards and refunds stay in the contract as internal balance, ready for the next bet or a
+// withdraw, and the user's auto-roll setting is applied to the winnings among them. Returns
+// the balance afterwards.
+fn claim_to_balance(
+    storage: &mut dyn Storage,
+    env: &Env,
+    user_addr: &Addr,
+    epochs: &[u64],
+) -> Result<(ClaimedRewards, Uint128), ContractError> {
+    let mut claimed = claim_rewards(storage, env, user_addr, epochs)?;
+    let mut balance = credit_balance(storage, user_addr, claimed.total_reward)?;
+
+    if let Some(position) = claimed.last_position.clone() {
+        if let Some((rolled, event)) = auto_roll(storage, env, user_addr, claimed.winnings, position)? {
+            balance = BALANCES.may_load(storage, user_addr.clone())?.unwrap_or_default();
+            claimed.rolled = rolled;
+            claimed.events.push(event);
+        }
+    }
+
+    Ok((claimed, balance))
+}
+
+// Reinvests part of a claimed payout into the open round according to the user's
+// auto-roll setting and returns the amount rolled. The roll is skipped, not failed, when the
+// bet can't be placed.
+fn auto_roll(
+    storage: &mut dyn Storage,
+    env: &Env,
+    user_addr: &Addr,
+    payout: Uint128,
+    won_position: Position,
+) -> Result<Option<(Uint128, Event)>, ContractError> {
+    let roll = match AUTO_ROLL.may_load(storage, user_addr.clone())? {
+        Some(roll) => roll,
+        None => return Ok(None),
+    };
+    if PAUSED.load(storage)? {
+        return Ok(None);
+    }
+
+    let config = CONFIG.load(storage)?;
+    let epoch = CURRENT_EPOCH.load(storage)?;
+    let round = match ROUNDS.may_load(storage, epoch)? {
+        Some(round) => round,
+        None => return Ok(None),
+    };
+
+    let amount = payout.mul_floor(roll.fraction);
+    let position = match roll.position_strategy {
+        RollStrategy::SameSide => won_position,
+        RollStrategy::Opposite => match won_position {
+            Position::Bull => Position::Bear,
+            Position::Bear => Position::Bull,
+            Position::Calm => Position::Volatile,
+            Position::Volatile => Position::Calm,
+            Position::Flat | Position::Bucket(_) | Position::Yes | Position::No => return Ok(None),
+        },
+        RollStrategy::FollowMajority => {
+            let sides = offered_positions(&round);
+            let largest = sides.iter().map(|side| pool_amount(&round, side)).max().unwrap_or_default();
+            let leaders: Vec<Position> = sides
+                .into_iter()
+                .filter(|side| pool_amount(&round, side) == largest)
+                .collect();
+            // A tie for the largest pool keeps the side that just won
+            match leaders.as_slice() {
+                [leader] => leader.clone(),
+                _ => won_position,
+            }
+        }
+    };
+
+    if place_bet(storage, env, &config, user_addr, epoch, amount, position.clone()).is_err() {
+        return Ok(None);
+    }
+    debit_balance(storage, user_addr, amount)?;
+
+    Ok(Some((amount, Event::new("auto_roll")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("user", user_addr.to_string())
+        .add_attribute("position", position_str(&position))
+        .add_attribute("amount", amount.to_string()))))
+}
+
+fn execute_set_auto_roll(
+    deps: DepsMut,
+    info: MessageInfo,
+    position_strategy: RollStrategy,
+    fraction: Decimal,
+) -> Result<Response, ContractError> {
+    if fraction > Decimal::one() {
+        return Err(ContractError::InvalidRollFraction {});
+    }
+
+    let strategy_str = match position_strategy {
+        RollStrategy::SameSide => "same_side",
+        RollStrategy::Opposite => "opposite",
+        RollStrategy::FollowMajority => "follow_majority",
+    };
+
+    // A zero fraction turns auto-roll off
+    if fraction.is_zero() {
+        AUTO_ROLL.remove(deps.storage, info.sender.clone());
+    } else {
+        AUTO_ROLL.save(deps.storage, info.sender.clone(), &AutoRoll {
+            position_strategy,
+            fraction,
+        })?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "set_auto_roll")
+        .add_attribute("user", info.sender)
+        .add_attribute("position_strategy", strategy_str)
+        .add_attribute("fraction", fraction.to_string()))
+}
+
+// Anyone can trigger a claim for a bettor; the reward always goes to the bettor. The part
+// their auto-roll setting reinvests stays in the contract as a bet, the rest is sent to them.
+fn execute_claim_for(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    user: String,
+    epochs: Vec<u64>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let user_addr = deps.api.addr_validate(&user)?;
+
+    let (claimed, _) = claim_to_balance(deps.storage, &env, &user_addr, &epochs)?;
+    let payout = claimed.total_reward.checked_sub(claimed.rolled)?;
+
+    let mut response = Response::new();
+    if !payout.is_zero() {
+        debit_balance(deps.storage, &user_addr, payout)?;
+        response = response.add_submessage(SubMsg::new(transfer_msg(&config, &user_addr, payout)?));
+    }
+
+    Ok(response
+        .add_attribute("method", "claim_for")
+        .add_attribute("caller", info.sender)
+        .add_attribute("user", user_addr)
+        .add_attribute("total_reward", claimed.total_reward.to_string())
+        .add_attribute("paid", payout.to_string())
+        .add_events(claimed.events))
+}
+
+struct ClaimedRewards {
+    total_reward: Uint128,
+    winnings: Uint128, // Part of total_reward won rather than refunded
+    rolled: Uint128,   // Part of winnings bet again by auto-roll
+    last_position: Option<Position>,
+    events: Vec<Event>,
+}
+
+fn claim_rewards(
+    storage: &mut dyn Storage,
+    env: &Env,
+    user_addr: &Addr,
+    epochs: &[u64],
+) -> Result<ClaimedRewards, ContractError> {
+    if epochs.is_empty() {
+        return Err(ContractError::EmptyEpochs {});
+    }
+
+    let mut total_reward = Uint128::zero();
+    let mut winnings = Uint128::zero();
+    let mut last_position = None;
+    let mut events = Vec::new();
+
+    for epoch in epochs.iter() {
+        let round = ROUNDS.load(storage, *epoch)?;
+        if !round.oracle_called {
+            return Err(ContractError::RoundNotEnded { epoch: *epoch });
+        }
+        if !claims_open(storage, env, &round)? {
+            return Err(ContractError::ClaimsNotOpen { epoch: *epoch });
+        }
+        release_round_fee(storage, *epoch)?;
+
+        if !LEDGER.has(storage, (*epoch, user_addr.clone())) {
+            return Err(ContractError::NoBetRecord { epoch: *epoch });
+        }
+
+        let mut bet_info = LEDGER.load(storage, (*epoch, user_addr.clone()))?;
+        if bet_info.claimed {
+            return Err(ContractError::AlreadyClaimed { epoch: *epoch });
+        }
+
+        let refund = is_refund_round(&round);
+        let reward = if refund {
+            refund_amount(&round, &bet_info)
+        } else {
+            calculate_reward(round.clone(), bet_info.clone())?
+        };
+        if reward == Uint128::zero() {
+            return Err(ContractError::NotWinner { epoch: *epoch });
+        }
+
+        bet_info.claimed = true;
+        LEDGER.save(storage, (*epoch, user_addr.clone()), &bet_info)?;
+
+        total_reward += reward;
+        if !refund {
+            winnings += reward;
+            last_position = Some(bet_info.position);
+        }
+
+        events.push(Event::new(if refund { "refund" } else { "claim" })
+            .add_attribute("epoch", epoch.to_string())
+            .add_attribute("user", user_addr.to_string())
+            .add_attribute("reward", reward.to_string()));
+    }
+
+    Ok(ClaimedRewards {
+        total_reward,
+        winnings,
+        rolled: Uint128::zero(),
+        last_position,
+        events,
+    })
+}
+
+// Pays out every unclaimed winner of a settled round, one page of bettors at a time.
+fn execute_push_payouts(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    epoch: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.operator_address {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !config.push_payouts_enabled {
+        return Err(ContractError::PushPayoutsDisabled {});
+    }
+
+    let round = ROUNDS.load(deps.storage, epoch)?;
+    if !round.oracle_called {
+        return Err(ContractError::RoundNotEnded { epoch });
+    }
+    if !claims_open(deps.storage, &env, &round)? {
+        return Err(ContractError::ClaimsNotOpen { epoch });
+    }
+    release_round_fee(deps.storage, epoch)?;
+
+    let start = match start_after {
+        Some(addr) => Some(Bound::exclusive(deps.api.addr_validate(&addr)?)),
+        None => None,
+    };
+    let limit = limit.unwrap_or(DEFAULT_PAYOUT_LIMIT).min(MAX_PAYOUT_LIMIT) as usize;
+
+    let bets = LEDGER
+        .prefix(epoch)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let last_user = bets.last().map(|(user, _)| user.clone());
+    let refund = is_refund_round(&round);
+    let mut total_paid = Uint128::zero();
+    let mut messages = Vec::new();
+    let mut events = Vec::new();
+
+    for (user_addr, mut bet_info) in bets {
+        if bet_info.claimed {
+            continue;
+        }
+
+        let reward = if refund {
+            refund_amount(&round, &bet_info)
+        } else {
+            calculate_reward(round.clone(), bet_info.clone())?
+        };
+        if reward == Uint128::zero() {
+            continue;
+        }
+
+        bet_info.claimed = true;
+        LEDGER.save(deps.storage, (epoch, user_addr.clone()), &bet_info)?;
+
+        total_paid += reward;
+        messages.push(SubMsg::new(transfer_msg(&config, &user_addr, reward)?));
+        events.push(Event::new(if refund { "refund" } else { "claim" })
+            .add_attribute("epoch", epoch.to_string())
+            .add_attribute("user", user_addr.to_string())
+            .add_attribute("reward", reward.to_string()));
+    }
+
+    let mut response = Response::new()
+        .add_submessages(messages)
+        .add_attribute("method", "push_payouts")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("total_paid", total_paid.to_string())
+        .add_events(events);
+    if let Some(last_user) = last_user {
+        response = response.add_attribute("last_user", last_user);
+    }
+
+    Ok(response)
+}
+
+fn transfer_msg(config: &Config, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: config.usdc_token.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    }))
+}
+
+fn calculate_reward(round: Round, bet_info: BetInfo) -> Result<Uint128, ContractError> {
+    if is_refund_round(&round) || winning_position(&round) != Some(bet_info.position.clone()) {
+        return Ok(Uint128::zero());
+    }
+
+    let position_amount = pool_amount(&round, &bet_info.position);
+    parimutuel_reward(round.total_amount, round.reward_base_amount, position_amount, bet_info.amount)
+}
+
+// Winners split the pot net of the treasury fee in proportion to their stake
+fn parimutuel_reward(
+    total_amount: Uint128,
+    fee_amount: Uint128,
+    winning_amount: Uint128,
+    stake: Uint128,
+) -> Result<Uint128, ContractError> {
+    let reward_base = total_amount.checked_sub(fee_amount)?;
+    let reward_amount = reward_base.checked_mul(stake)?.checked_div(winning_amount)?;
+
+    Ok(reward_amount)
+}
+
+// Flat wins while the move stays within the round's band around the lock price.
+// Outside the band the direction decides, and an exact tie has no winner.
+fn winning_position(round: &Round) -> Option<Position> {
+    match &round.kind {
+        MarketKind::Range { boundaries_bps } => {
+            return Some(Position::Bucket(range_bucket(round, boundaries_bps)));
+        }
+        MarketKind::StrikeOffset { offset } => return offset_winner(round, offset),
+        MarketKind::Relative { .. } => return relative_winner(round),
+        MarketKind::Volatility { threshold_bps } => return Some(volatility_winner(round, *threshold_bps)),
+        MarketKind::UpDown => {}
+    }
+
+    if let Some(flat_band_bps) = round.flat_band_bps {
+        let price_move = round.close_price.abs_diff(round.lock_price);
+        let band = round.lock_price.unsigned_abs().saturating_mul(u128::from(flat_band_bps));
+        if price_move.saturating_mul(10000) <= band {
+            return Some(Position::Flat);
+        }
+    }
+
+    match round.close_price.cmp(&round.lock_price) {
+        Ordering::Greater => Some(Position::Bull),
+        Ordering::Less => Some(Position::Bear),
+        Ordering::Equal => None,
+    }
+}
+
+// Bucket i holds moves between boundaries i - 1 and i, in basis points of the lock
+// price. A move landing exactly on a boundary falls into the bucket above it.
+fn range_bucket(round: &Round, boundaries_bps: &[i64]) -> u32 {
+    let price_move = round.close_price.saturating_sub(round.lock_price).saturating_mul(10000);
+    boundaries_bps
+        .iter()
+        .filter(|boundary| price_move >= round.lock_price.saturating_mul(i128::from(**boundary)))
+        .count() as u32
+}
+
+// Bull has to clear lock_price + offset and Bear has to clear lock_price - offset;
+// a close in between has no winner and the round is refunded.
+fn offset_winner(round: &Round, offset: &StrikeOffset) -> Option<Position> {
+    let offset = match offset {
+        StrikeOffset::Bps(bps) => {
+            let offset = round.lock_price.unsigned_abs().saturating_mul(u128::from(*bps)) / 10000;
+            i128::try_from(offset).unwrap_or(i128::MAX)
+        }
+        StrikeOffset::Absolute(amount) => i128::try_from(amount.u128()).unwrap_or(i128::MAX),
+    };
+
+    if round.close_price > round.lock_price.saturating_add(offset) {
+        Some(Position::Bull)
+    } else if round.close_price < round.lock_price.saturating_sub(offset) {
+        Some(Position::Bear)
+    } else {
+        None
+    }
+}
+
+// Bull backs asset A gaining more than asset B between lock and close, Bear the
+// reverse. Returns are compared by cross-multiplying, so prices must be positive.
+fn relative_winner(round: &Round) -> Option<Position> {
+    let return_a = round.close_price.saturating_mul(round.lock_price_b);
+    let return_b = round.close_price_b.saturating_mul(round.lock_price);
+
+    match return_a.cmp(&return_b) {
+        Ordering::Greater => Some(Position::Bull),
+        Ordering::Less => Some(Position::Bear),
+        Ordering::Equal => None,
+    }
+}
+
+// Volatile wins when the move exceeds threshold_bps of the lock price in either
+// direction; otherwise Calm wins.
+fn volatility_winner(round: &Round, threshold_bps: u64) -> Position {
+    let price_move = round.close_price.abs_diff(round.lock_price);
+    let threshold = round.lock_price.unsigned_abs().saturating_mul(u128::from(threshold_bps));
+
+    if price_move.saturating_mul(10000) > threshold {
+        Position::Volatile
+    } else {
+        Position::Calm
+    }
+}
+
+// A settled round pays every stake back when it was cancelled, there is no winning
+// side, nobody picked it, or nobody bet against it.
+fn is_refund_round(round: &Round) -> bool {
+    if round.cancelled {
+        return true;
+    }
+
+    match winning_position(round) {
+        Some(position) => is_one_sided(pool_amount(round, &position), round.total_amount),
+        None => true,
+    }
+}
+
+// Stake returned by a refunded round. Only legacy rounds still carry a fee when refunded,
+// taken into the treasury at lock, so their bettors share what is left of the pool.
+fn refund_amount(round: &Round, bet_info: &BetInfo) -> Uint128 {
+    if round.reward_base_amount.is_zero() {
+        return bet_info.amount;
+    }
+    bet_info.amount.multiply_ratio(round.total_amount - round.reward_base_amount, round.total_amount)
+}
+
+fn is_one_sided(winning_amount: Uint128, total_amount: Uint128) -> bool {
+    winning_amount.is_zero() || winning_amount == total_amount
+}
+
+fn execute_round(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    attestations: Vec<PriceAttestation>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.operator_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let paused = PAUSED.load(deps.storage)?;
+    if paused {
+        return Err(ContractError::Paused {});
+    }
+
+    let current_epoch = CURRENT_EPOCH.load(deps.storage)?;
+    if current_epoch == 0 {
+        return Err(ContractError::GenesisNotStarted {});
+    }
+
+    if settlement_halted(deps.storage)? {
+        return Err(ContractError::SettlementHalted {});
+    }
+
+    let current_timestamp = env.block.time.seconds();
+    let mut events = Vec::new();
+    let attested_keys = record_attestations(deps.branch(), &env, &config, attestations)?;
+
+    // End every earlier round that has reached its close_timestamp. With a long buffer the
+    // next round can lock before the previous one closes, so more than one may be waiting.
+    // A circuit breaker trip stops progression where it happened, and rounds of the first
+    // release are never reached.
+    let legacy_epoch = LEGACY_EPOCH.may_load(deps.storage)?.unwrap_or_default();
+    let mut epoch = current_epoch - 1;
+    while epoch > legacy_epoch && !settlement_halted(deps.storage)? {
+        let round = ROUNDS.load(deps.storage, epoch)?;
+        if round.oracle_called {
+            break;
+        }
+        if current_timestamp >= round.close_timestamp {
+            events.extend(execute_end_round(&mut deps, env.clone(), epoch, &config)?.events);
+        }
+        epoch -= 1;
+    }
+
+    // Lock the current round at its lock_timestamp, then open the next one
+    let mut current_round = ROUNDS.load(deps.storage, current_epoch)?;
+    if !settlement_halted(deps.storage)? && !is_locked(&current_round) && current_timestamp >= current_round.lock_timestamp {
+        events.extend(execute_lock_round(&mut deps, env.clone(), current_epoch, &config)?.events);
+        current_round = ROUNDS.load(deps.storage, current_epoch)?;
+    }
+    if !settlement_halted(deps.storage)? && is_locked(&current_round) {
+        events.extend(execute_start_round(&mut deps, env, current_epoch + 1, &config)?.events);
+    }
+
+    // Attested prices only price the rounds handled in this call
+    for (phase, key) in attested_keys {
+        attested_prices(phase).remove(deps.storage, key);
+    }
+
+    let mut response = Response::new()
+        .add_attribute("method", "execute_round")
+        .add_attribute("current_epoch", current_epoch.to_string())
+        .add_attribute("current_timestamp", current_timestamp.to_string());
+    if events.is_empty() {
+        response = response.add_attribute("action", "no_action_needed");
+    }
+
+    Ok(response.add_events(events))
+}
+
+// Verifies each attestation against a configured Attested source and stores its price for
+// this call. An attestation is accepted once; its epoch ties it to a single round.
+fn record_attestations(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    attestations: Vec<PriceAttestation>,
+) -> Result<Vec<(PricePhase, AttestedKey)>, ContractError> {
+    let mut keys = Vec::with_capacity(attestations.len());
+
+    for attestation in attestations {
+        let scheme = config
+            .price_sources
+            .iter()
+            .chain(config.fallback_price_source.iter())
+            .find_map(|source| match source {
+                PriceSource::Attested { public_key, scheme } if *public_key == attestation.public_key => Some(scheme),
+                _ => None,
+            })
+            .ok_or(ContractError::Unauthorized {})?;
+
+        if attestation.price.is_zero() || attestation.timestamp > env.block.time.seconds() {
+            return Err(ContractError::InvalidAttestation {});
+        }
+        let price = i128::try_from(attestation.price.u128()).map_err(|_| ContractError::InvalidAttestation {})?;
+
+        let digest = attestation_digest(&env.contract.address, &attestation);
+        if USED_ATTESTATIONS.has(deps.storage, digest.to_vec()) {
+            return Err(ContractError::AttestationReplayed {});
+        }
+        verify_attestation(deps.api, scheme, &digest, &attestation.signature, &attestation.public_key)?;
+        USED_ATTESTATIONS.save(deps.storage, digest.to_vec(), &true)?;
+
+        let key = (attestation.price_feed_id, attestation.epoch, attestation.public_key.to_vec());
+        attested_prices(attestation.phase).save(deps.storage, key.clone(), &PriceInfo {
+            price,
+            expo: attestation.expo,
+            conf: 0,
+            publish_time: attestation.timestamp,
+        })?;
+        keys.push((attestation.phase, key));
+    }
+
+    Ok(keys)
+}
+
+// A round is locked once it has a lock price, or was cancelled instead of locking
+fn is_locked(round: &Round) -> bool {
+    round.lock_price_info.is_some() || round.cancelled
+}
+
+fn execute_start_round(
+    deps: &mut DepsMut,
+    env: Env,
+    epoch: u64,
+    config: &Config,
+) -> Result<Response, ContractError> {
+    let start_timestamp = env.block.time.seconds();
+    let lock_timestamp = start_timestamp + config.interval_seconds - config.buffer_seconds;
+    let close_timestamp = start_timestamp + config.interval_seconds;
+
+    let (flat_band_bps, bucket_amounts) = match &config.market_kind {
+        MarketKind::UpDown => (config.flat_band_bps, vec![]),
+        MarketKind::Range { boundaries_bps } => (None, vec![Uint128::zero(); boundaries_bps.len() + 1]),
+        MarketKind::StrikeOffset { .. }
+        | MarketKind::Relative { .. }
+        | MarketKind::Volatility { .. } => (None, vec![]),
+    };
+
+    let new_round = Round {
+        epoch,
+        kind: config.market_kind.clone(),
+        start_timestamp,
+        lock_timestamp,
+        close_timestamp,
+        lock_price: 0,
+        close_price: 0,
+        lock_price_b: 0,
+        close_price_b: 0,
+        lock_price_info: None,
+        close_price_info: None,
+        lock_price_origin: None,
+        close_price_origin: None,
+        total_amount: Uint128::zero(),
+        bull_amount: Uint128::zero(),
+        bear_amount: Uint128::zero(),
+        flat_amount: Uint128::zero(),
+        calm_amount: Uint128::zero(),
+        volatile_amount: Uint128::zero(),
+        flat_band_bps,
+        bucket_amounts,
+        reward_base_amount: Uint128::zero(),
+        reward_amount: Uint128::zero(),
+        oracle_called: false,
+        claims_open_at: 0,
+        cancelled: false,
+        flagged: false,
+    };
+
+    ROUNDS.save(deps.storage, epoch, &new_round)?;
+    CURRENT_EPOCH.save(deps.storage, &epoch)?;
+
+    let (order_events, orders_done) = fill_standing_orders(deps.storage, &env, epoch, config, DEFAULT_FILL_LIMIT)?;
+
+    let event = Event::new("start_round")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("start_timestamp", start_timestamp.to_string())
+        .add_attribute("lock_timestamp", lock_timestamp.to_string())
+        .add_attribute("close_timestamp", close_timestamp.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_events(order_events)
+        .add_attribute("method", "start_round")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("standing_orders_done", orders_done.to_string()))
+}
+
+fn execute_lock_round(
+    deps: &mut DepsMut,
+    env: Env,
+    epoch: u64,
+    config: &Config,
+) -> Result<Response, ContractError> {
+    let mut round = ROUNDS.load(deps.storage, epoch)?;
+
+    // Sources that disagree, or a round no source can price at its lock_timestamp, cancel
+    // the round instead of locking it
+    let prices = match round_prices(deps.as_ref(), &env, config, &round, PricePhase::Lock) {
+        Ok(prices) => prices,
+        Err(err) => {
+            round.cancelled = true;
+            ROUNDS.save(deps.storage, epoch, &round)?;
+
+            return Ok(Response::new()
+                .add_event(cancel_round_event(epoch, &err.to_string()))
+                .add_attribute("method", "lock_round")
+                .add_attribute("epoch", epoch.to_string()));
+        }
+    };
+    round.lock_price = prices.info.price;
+    round.lock_price_info = Some(prices.info.clone());
+    round.lock_price_origin = Some(prices.origin.clone());
+
+    let mut event = Event::new("lock_round")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("lock_timestamp", env.block.time.seconds().to_string())
+        .add_attribute("lock_price", prices.info.price.to_string())
+        .add_attribute("expo", prices.info.expo.to_string())
+        .add_attribute("publish_time", prices.info.publish_time.to_string())
+        .add_attribute("price_origin", price_origin_str(&prices.origin));
+
+    if let Some(lock_price_b) = prices.price_b {
+        round.lock_price_b = lock_price_b;
+        event = event.add_attribute("lock_price_b", round.lock_price_b.to_string());
+    }
+
+    let mut response = Response::new()
+        .add_event(event)
+        .add_attribute("method", "lock_round")
+        .add_attribute("epoch", epoch.to_string());
+
+    // A jump from the previous round's lock reading is held for review. Relative rounds
+    // also check their second feed when the previous round priced the same pair.
+    let previous = match epoch {
+        1 => None,
+        _ => ROUNDS.may_load(deps.storage, epoch - 1)?,
+    };
+    if let Some(previous) = previous {
+        let mut moves = vec![];
+        if let Some(previous_info) = previous.lock_price_info.as_ref().filter(|info| info.expo == prices.info.expo) {
+            moves.push((previous_info.price, prices.info.price));
+        }
+        if previous.lock_price_info.is_some() && previous.kind == round.kind && prices.price_b.is_some() {
+            moves.push((previous.lock_price_b, round.lock_price_b));
+        }
+        if let Some((from, to, move_bps)) = largest_price_move(config, &moves) {
+            let events = trip_circuit_breaker(deps.storage, config, &mut round, "round_to_round", from, to, move_bps)?;
+            response = response.add_events(events);
+        }
+    }
+
+    ROUNDS.save(deps.storage, epoch, &round)?;
+
+    Ok(response)
+}
+
+fn execute_end_round(
+    deps: &mut DepsMut,
+    env: Env,
+    epoch: u64,
+    config: &Config,
+) -> Result<Response, ContractError> {
+    let mut round = ROUNDS.load(deps.storage, epoch)?;
+
+    // A cancelled round is closed without a price and refunds at once
+    if round.cancelled {
+        round.oracle_called = true;
+        round.claims_open_at = env.block.time.seconds();
+        ROUNDS.save(deps.storage, epoch, &round)?;
+
+        return Ok(Response::new()
+            .add_attribute("method", "end_round")
+            .add_attribute("epoch", epoch.to_string())
+            .add_attribute("cancelled", "true"));
+    }
+
+    let prices = match round_prices(deps.as_ref(), &env, config, &round, PricePhase::Close) {
+        Ok(prices) => prices,
+        Err(err) => {
+            round.cancelled = true;
+            round.oracle_called = true;
+            round.claims_open_at = env.block.time.seconds();
+            ROUNDS.save(deps.storage, epoch, &round)?;
+
+            return Ok(Response::new()
+                .add_event(cancel_round_event(epoch, &err.to_string()))
+                .add_attribute("method", "end_round")
+                .add_attribute("epoch", epoch.to_string())
+                .add_attribute("cancelled", "true"));
+        }
+    };
+    round.close_price = prices.info.price;
+    round.close_price_info = Some(prices.info.clone());
+    round.close_price_origin = Some(prices.origin.clone());
+    if let Some(close_price_b) = prices.price_b {
+        round.close_price_b = close_price_b;
+    }
+    round.oracle_called = true;
 
-    let close_price = get_mock_btc_price();
+    // A move from lock to close beyond the limit on either feed makes the round refundable
+    // and halts settlement
+    let mut moves = vec![];
+    if round.lock_price_info.as_ref().map(|info| info.expo) == Some(prices.info.expo) {
+        moves.push((round.lock_price, prices.info.price));
+    }
+    if prices.price_b.is_some() {
+        moves.push((round.lock_price_b, round.close_price_b));
+    }
+    let breaker_events = match largest_price_move(config, &moves) {
+        Some((from, to, move_bps)) => {
+            round.claims_open_at = env.block.time.seconds();
+            trip_circuit_breaker(deps.storage, config, &mut round, "lock_to_close", from, to, move_bps)?
+        }
+        None => {
+            round.claims_open_at = env.block.time.seconds()
+                + config.settlement_challenge.as_ref().map_or(0, |challenge| challenge.window_seconds);
+            take_round_fee(deps.storage, config, &mut round)?;
+            if round.claims_open_at <= env.block.time.seconds() {
+                release_round_fee(deps.storage, epoch)?;
+            }
+            vec![]
+        }
+    };
 
-    let mut round = ROUNDS.load(deps.storage, epoch)?;
-    round.close_price = close_price;
-    round.oracle_called = true;
     ROUNDS.save(deps.storage, epoch, &round)?;
 
-    let event = Event::new("end_round")
+    let mut event = Event::new("end_round")
         .add_attribute("epoch", epoch.to_string())
         .add_attribute("close_timestamp", env.block.time.seconds().to_string())
-        .add_attribute("close_price", close_price.to_string());
+        .add_attribute("close_price", prices.info.price.to_string())
+        .add_attribute("expo", prices.info.expo.to_string())
+        .add_attribute("publish_time", prices.info.publish_time.to_string())
+        .add_attribute("price_origin", price_origin_str(&prices.origin))
+        .add_attribute("claims_open_at", round.claims_open_at.to_string());
+    if prices.price_b.is_some() {
+        event = event.add_attribute("close_price_b", round.close_price_b.to_string());
+    }
 
     Ok(Response::new()
         .add_event(event)
+        .add_events(breaker_events)
         .add_attribute("method", "end_round")
         .add_attribute("epoch", epoch.to_string()))
 }
 
+// Move from `from` to `to` in bps of `from`, when it is beyond the circuit breaker limit
+fn price_move_exceeded(config: &Config, from: i128, to: i128) -> Option<u128> {
+    let max_price_move_bps = config.max_price_move_bps?;
+    if from <= 0 {
+        return None;
+    }
+
+    let move_bps = from.abs_diff(to) * 10000 / from.unsigned_abs();
+    (move_bps > u128::from(max_price_move_bps)).then_some(move_bps)
+}
+
+// The largest of the (from, to) moves beyond the limit, if any
+fn largest_price_move(config: &Config, moves: &[(i128, i128)]) -> Option<(i128, i128, u128)> {
+    moves
+        .iter()
+        .filter_map(|&(from, to)| price_move_exceeded(config, from, to).map(|move_bps| (from, to, move_bps)))
+        .max_by_key(|&(_, _, move_bps)| move_bps)
+}
+
+fn settlement_halted(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(SETTLEMENT_HALTED.may_load(storage)?.unwrap_or_default())
+}
+
+// Flags the round for review, makes it refundable and halts settlement until the admin resumes it
+fn trip_circuit_breaker(
+    storage: &mut dyn Storage,
+    config: &Config,
+    round: &mut Round,
+    check: &str,
+    from_price: i128,
+    to_price: i128,
+    move_bps: u128,
+) -> Result<Vec<Event>, ContractError> {
+    round.cancelled = true;
+    round.flagged = true;
+    SETTLEMENT_HALTED.save(storage, &true)?;
+
+    let event = Event::new("circuit_breaker")
+        .add_attribute("epoch", round.epoch.to_string())
+        .add_attribute("check", check)
+        .add_attribute("from_price", from_price.to_string())
+        .add_attribute("to_price", to_price.to_string())
+        .add_attribute("move_bps", move_bps.to_string())
+        .add_attribute("max_price_move_bps", config.max_price_move_bps.unwrap_or_default().to_string());
+
+    Ok(vec![event, cancel_round_event(round.epoch, "circuit_breaker")])
+}
+
+// The admin or guardian can cancel a round until its claims open, so a settlement on a bad
+// price can still be undone during the challenge window. Every bettor reclaims their stake.
+fn execute_cancel_round(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    epoch: u64,
+    reason: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address && Some(&info.sender) != config.guardian_address.as_ref() {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut round = ROUNDS.load(deps.storage, epoch)?;
+    if round.cancelled {
+        return Err(ContractError::RoundCancelled { epoch });
+    }
+    if round.oracle_called && claims_open(deps.storage, &env, &round)? {
+        return Err(ContractError::RoundSettled { epoch });
+    }
+
+    // Drop the fee held back at settlement so every stake can be refunded; it never
+    // reached the treasury because claims have not opened. A legacy round already paid its
+    // fee into the treasury at lock, so it keeps it and refunds what is left.
+    let legacy = epoch <= LEGACY_EPOCH.may_load(deps.storage)?.unwrap_or_default();
+    if !round.reward_base_amount.is_zero() && !legacy {
+        PENDING_ROUND_FEES.remove(deps.storage, epoch);
+        round.reward_base_amount = Uint128::zero();
+        round.reward_amount = Uint128::zero();
+    }
+
+    // A settled round refunds at once; an open or locked one when execute_round reaches its
+    // close. ExecuteRound never reaches a legacy round, so it refunds at once too.
+    if round.oracle_called || legacy {
+        round.oracle_called = true;
+        round.claims_open_at = env.block.time.seconds();
+    }
+    round.cancelled = true;
+    ROUNDS.save(deps.storage, epoch, &round)?;
+
+    // A pending challenge no longer has a settlement to correct, so its bond goes back
+    let mut response = Response::new();
+    if let Some(mut challenge) = CHALLENGES.may_load(deps.storage, epoch)? {
+        if challenge.upheld.is_none() {
+            credit_balance(deps.storage, &challenge.challenger, challenge.bond)?;
+            challenge.upheld = Some(true);
+            CHALLENGES.save(deps.storage, epoch, &challenge)?;
+
+            response = response.add_event(Event::new("resolve_challenge")
+                .add_attribute("epoch", epoch.to_string())
+                .add_attribute("challenger", challenge.challenger.to_string())
+                .add_attribute("upheld", "true")
+                .add_attribute("payout", challenge.bond.to_string()));
+        }
+    }
+
+    Ok(response
+        .add_event(cancel_round_event(epoch, &reason).add_attribute("cancelled_by", info.sender.to_string()))
+        .add_attribute("method", "cancel_round")
+        .add_attribute("epoch", epoch.to_string()))
+}
+
+fn cancel_round_event(epoch: u64, reason: &str) -> Event {
+    Event::new("cancel_round")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("reason", reason)
+}
+
+// The treasury fee is only taken from rounds that pay out; refunded rounds return every stake
+fn take_round_fee(
+    storage: &mut dyn Storage,
+    config: &Config,
+    round: &mut Round,
+) -> Result<(), ContractError> {
+    if is_refund_round(round) {
+        return Ok(());
+    }
+
+    let treasury_fee = round.total_amount * Uint128::from(config.treasury_fee) / Uint128::from(10000u32);
+    round.reward_base_amount = treasury_fee;
+    round.reward_amount = round.total_amount.checked_sub(treasury_fee)?;
+    PENDING_ROUND_FEES.save(storage, round.epoch, &treasury_fee)?;
+
+    Ok(())
+}
+
+// Moves a round's pending fee into the treasury once its claims have opened
+fn release_round_fee(storage: &mut dyn Storage, epoch: u64) -> StdResult<()> {
+    if let Some(fee) = PENDING_ROUND_FEES.may_load(storage, epoch)? {
+        PENDING_ROUND_FEES.remove(storage, epoch);
+        let treasury = TREASURY.load(storage)?.checked_add(fee)?;
+        TREASURY.save(storage, &treasury)?;
+    }
+    Ok(())
+}
+
+// Moves a market's pending fee into the treasury once its claims have opened
+fn release_market_fee(storage: &mut dyn Storage, market_id: u64) -> StdResult<()> {
+    if let Some(fee) = PENDING_MARKET_FEES.may_load(storage, market_id)? {
+        PENDING_MARKET_FEES.remove(storage, market_id);
+        let treasury = TREASURY.load(storage)?.checked_add(fee)?;
+        TREASURY.save(storage, &treasury)?;
+    }
+    Ok(())
+}
+
+// Releases the oldest pending fees whose claims have opened, up to FEE_RELEASE_LIMIT per map
+fn release_pending_fees(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    let round_fees = PENDING_ROUND_FEES
+        .keys(storage, None, None, Order::Ascending)
+        .take(FEE_RELEASE_LIMIT)
+        .collect::<StdResult<Vec<_>>>()?;
+    for epoch in round_fees {
+        let round = ROUNDS.load(storage, epoch)?;
+        if claims_open(storage, env, &round)? {
+            release_round_fee(storage, epoch)?;
+        }
+    }
+
+    let market_fees = PENDING_MARKET_FEES
+        .keys(storage, None, None, Order::Ascending)
+        .take(FEE_RELEASE_LIMIT)
+        .collect::<StdResult<Vec<_>>>()?;
+    for market_id in market_fees {
+        let market = MARKETS.load(storage, market_id)?;
+        if market_claims_open(env, &market) {
+            release_market_fee(storage, market_id)?;
+        }
+    }
+
+    Ok(())
+}
+
+// A settled round pays out once its challenge window has passed and any challenge is decided
+fn claims_open(storage: &dyn Storage, env: &Env, round: &Round) -> StdResult<bool> {
+    if env.block.time.seconds() < round.claims_open_at {
+        return Ok(false);
+    }
+
+    let pending = CHALLENGES
+        .may_load(storage, round.epoch)?
+        .is_some_and(|challenge| challenge.upheld.is_none());
+    Ok(!pending)
+}
+
+// Disputes a round's close price during its challenge window. The bond is paid like a bet,
+// from attached funds or the internal balance. The Pyth price update is applied on-chain and
+// checked by VerifyChallenge in the same transaction.
+fn execute_challenge_settlement(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    epoch: u64,
+    close_price: Uint128,
+    close_price_b: Option<Uint128>,
+    price_update: Vec<Binary>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let bond = match &config.settlement_challenge {
+        Some(settlement_challenge) => settlement_challenge.bond_amount,
+        None => return Err(ContractError::ChallengesDisabled {}),
+    };
+
+    let round = ROUNDS.load(deps.storage, epoch)?;
+    if !round.oracle_called {
+        return Err(ContractError::RoundNotEnded { epoch });
+    }
+    if round.cancelled {
+        return Err(ContractError::RoundCancelled { epoch });
+    }
+    if env.block.time.seconds() >= round.claims_open_at {
+        return Err(ContractError::ChallengeWindowClosed { epoch });
+    }
+    if CHALLENGES.has(deps.storage, epoch) {
+        return Err(ContractError::AlreadyChallenged { epoch });
+    }
+
+    let close_price = i128::try_from(close_price.u128()).map_err(|_| ContractError::InvalidSettlementChallenge {})?;
+    let close_price_b = close_price_b
+        .map(|price| i128::try_from(price.u128()).map_err(|_| ContractError::InvalidSettlementChallenge {}))
+        .transpose()?;
+    if close_price == round.close_price && close_price_b.is_none_or(|price| price == round.close_price_b) {
+        return Err(ContractError::ChallengePriceUnchanged { epoch });
+    }
+    if price_update.is_empty() {
+        return Err(ContractError::InvalidSettlementChallenge {});
+    }
+
+    // A price the settlement's own confidence band already allows is no evidence of error
+    let settled_conf = round.close_price_info.as_ref().map_or(0, |info| info.conf);
+    if close_price != round.close_price && close_price.abs_diff(round.close_price) <= u128::from(settled_conf) {
+        return Err(ContractError::ChallengeWithinConfidence { epoch });
+    }
+
+    let mut hasher = Sha256::new();
+    for update in price_update.iter() {
+        hasher.update(update.as_slice());
+    }
+    let evidence_hash = hex::encode(hasher.finalize());
+
+    // The Pyth update fee is sent alongside the bond, which comes from the funds or the balance
+    let update_fee = query_update_fee(&deps.querier, &config.oracle_address, &price_update)?;
+    let sent = |denom: &str| info.funds.iter().filter(|c| c.denom == denom).map(|c| c.amount).sum::<Uint128>();
+    let fee_in_usdc = if update_fee.denom == config.usdc_token { update_fee.amount } else { Uint128::zero() };
+    if update_fee.denom != config.usdc_token && sent(&update_fee.denom) != update_fee.amount {
+        return Err(ContractError::InvalidBetFunds {});
+    }
+    let bond_sent = sent(config.usdc_token.as_str())
+        .checked_sub(fee_in_usdc)
+        .map_err(|_| ContractError::InvalidBetFunds {})?;
+    let funding = if bond_sent.is_zero() {
+        debit_balance(deps.storage, &info.sender, bond)?;
+        "balance"
+    } else if bond_sent == bond {
+        "funds"
+    } else {
+        return Err(ContractError::InvalidBetFunds {});
+    };
+
+    CHALLENGES.save(deps.storage, epoch, &Challenge {
+        challenger: info.sender.clone(),
+        bond,
+        close_price,
+        close_price_b,
+        evidence_hash: evidence_hash.clone(),
+        upheld: None,
+    })?;
+
+    let event = Event::new("challenge_settlement")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("challenger", info.sender.to_string())
+        .add_attribute("settled_close_price", round.close_price.to_string())
+        .add_attribute("challenged_close_price", close_price.to_string())
+        .add_attribute("evidence_hash", evidence_hash);
+
+    // Apply the update on Pyth, then read it back in VerifyChallenge
+    let verify_msg = WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: to_json_binary(&ExecuteMsg::VerifyChallenge { epoch })?,
+        funds: vec![],
+    };
+
+    Ok(Response::new()
+        .add_message(update_price_feeds_msg(&config.oracle_address, price_update, update_fee)?)
+        .add_message(verify_msg)
+        .add_event(event)
+        .add_attribute("method", "challenge_settlement")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("bond", bond.to_string())
+        .add_attribute("funding", funding))
+}
+
+// Admin override for challenges the evidence did not settle. An upheld challenge resettles the
+// round on the challenged price; the challenger gets the bond back plus the round's treasury fee.
+// A rejected challenge forfeits the bond to the treasury.
+fn execute_resolve_challenge(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    epoch: u64,
+    upheld: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut challenge = match CHALLENGES.may_load(deps.storage, epoch)? {
+        Some(challenge) if challenge.upheld.is_none() => challenge,
+        _ => return Err(ContractError::NoOpenChallenge { epoch }),
+    };
+
+    let event = if upheld {
+        uphold_challenge(deps.storage, &config, epoch, &mut challenge)?
+    } else {
+        let treasury = TREASURY.load(deps.storage)?.checked_add(challenge.bond)?;
+        TREASURY.save(deps.storage, &treasury)?;
+        challenge.upheld = Some(false);
+        CHALLENGES.save(deps.storage, epoch, &challenge)?;
+        if claims_open(deps.storage, &env, &ROUNDS.load(deps.storage, epoch)?)? {
+            release_round_fee(deps.storage, epoch)?;
+        }
+
+        Event::new("resolve_challenge")
+            .add_attribute("epoch", epoch.to_string())
+            .add_attribute("challenger", challenge.challenger.to_string())
+            .add_attribute("upheld", "false")
+    };
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("method", "resolve_challenge")
+        .add_attribute("epoch", epoch.to_string()))
+}
+
+// Resettles the round on the challenged prices and pays the challenger
+fn uphold_challenge(
+    storage: &mut dyn Storage,
+    config: &Config,
+    epoch: u64,
+    challenge: &mut Challenge,
+) -> Result<Event, ContractError> {
+    let mut round = ROUNDS.load(storage, epoch)?;
+    PENDING_ROUND_FEES.remove(storage, epoch);
+    round.reward_base_amount = Uint128::zero();
+    round.reward_amount = Uint128::zero();
+
+    round.close_price = challenge.close_price;
+    if let Some(close_price_info) = round.close_price_info.as_mut() {
+        close_price_info.price = challenge.close_price;
+        close_price_info.conf = 0;
+    }
+    if let Some(close_price_b) = challenge.close_price_b {
+        round.close_price_b = close_price_b;
+    }
+    take_round_fee(storage, config, &mut round)?;
+    ROUNDS.save(storage, epoch, &round)?;
+
+    // The fee of the corrected settlement goes to the challenger instead of the treasury
+    PENDING_ROUND_FEES.remove(storage, epoch);
+    let payout = challenge.bond.checked_add(round.reward_base_amount)?;
+    credit_balance(storage, &challenge.challenger, payout)?;
+
+    challenge.upheld = Some(true);
+    CHALLENGES.save(storage, epoch, challenge)?;
+
+    Ok(Event::new("resolve_challenge")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("challenger", challenge.challenger.to_string())
+        .add_attribute("upheld", "true")
+        .add_attribute("close_price", round.close_price.to_string())
+        .add_attribute("payout", payout.to_string()))
+}
+
+// Reads back the price update applied by ChallengeSettlement. When Pyth holds a reading
+// published nearer the round's close than the settled one, which backs the challenged price,
+// rules out the settled one and changes the winner, the challenge is upheld at once; anything
+// less leaves it to the admin.
+fn execute_verify_challenge(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    epoch: u64,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut challenge = match CHALLENGES.may_load(deps.storage, epoch)? {
+        Some(challenge) if challenge.upheld.is_none() => challenge,
+        _ => return Err(ContractError::NoOpenChallenge { epoch }),
+    };
+    let round = ROUNDS.load(deps.storage, epoch)?;
+
+    let (proven, verified) = match challenge_evidence(deps.as_ref(), &config, &round, &challenge) {
+        Ok(proven) => (proven, proven.to_string()),
+        Err(err) => (false, err.to_string()),
+    };
+    let mut response = Response::new()
+        .add_event(Event::new("verify_challenge")
+            .add_attribute("epoch", epoch.to_string())
+            .add_attribute("verified", verified))
+        .add_attribute("method", "verify_challenge")
+        .add_attribute("epoch", epoch.to_string());
+
+    if proven {
+        response = response.add_event(uphold_challenge(deps.storage, &config, epoch, &mut challenge)?);
+    }
+
+    Ok(response)
+}
+
+// Whether Pyth's stored readings prove every challenged price and that it changes the winner
+fn challenge_evidence(
+    deps: Deps,
+    config: &Config,
+    round: &Round,
+    challenge: &Challenge,
+) -> Result<bool, ContractError> {
+    let settled_info = round.close_price_info.as_ref().ok_or(ContractError::InvalidSettlementChallenge {})?;
+    let (feed_id, feed_id_b) = round_feed_ids(round, config);
+
+    let mut claims = vec![(feed_id, round.close_price, settled_info.conf, challenge.close_price)];
+    if let (Some(feed_id_b), Some(close_price_b)) = (feed_id_b, challenge.close_price_b) {
+        claims.push((feed_id_b, round.close_price_b, 0, close_price_b));
+    }
+
+    for (feed_id, settled, settled_conf, claimed) in claims {
+        if claimed == settled {
+            continue;
+        }
+
+        let reading = query_price_unchecked(&deps.querier, &config.oracle_address, &feed_id)?;
+        check_price_window(config, &reading, round.close_timestamp)?;
+        if reading.expo != settled_info.expo {
+            return Ok(false);
+        }
+        // Only a reading published nearer the close than the settled one outranks it
+        let target = round.close_timestamp;
+        if reading.publish_time.abs_diff(target) >= settled_info.publish_time.abs_diff(target) {
+            return Ok(false);
+        }
+
+        let backs_claim = reading.price.abs_diff(claimed) <= u128::from(reading.conf);
+        let rules_out_settled = reading.price.abs_diff(settled) > u128::from(reading.conf) + u128::from(settled_conf);
+        if !backs_claim || !rules_out_settled {
+            return Ok(false);
+        }
+    }
+
+    // A correction that leaves the winner in place is not worth resettling without the admin
+    let mut resettled = round.clone();
+    resettled.close_price = challenge.close_price;
+    if let Some(close_price_b) = challenge.close_price_b {
+        resettled.close_price_b = close_price_b;
+    }
+    Ok(winning_position(&resettled) != winning_position(round))
+}
+
+// Feeds a round is priced from: the configured BTC feed, or both assets of a Relative round
+fn round_feed_ids(round: &Round, config: &Config) -> (String, Option<String>) {
+    match &round.kind {
+        MarketKind::Relative { feed_id_a, feed_id_b } => (feed_id_a.clone(), Some(feed_id_b.clone())),
+        _ => (config.btc_price_feed_id.clone(), None),
+    }
+}
+
+struct RoundPrices {
+    info: PriceInfo,
+    price_b: Option<i128>, // Second feed of a Relative round
+    origin: PriceOrigin,
+}
+
+// Lock or close prices of a round's feed, and of its second feed for Relative rounds,
+// published within the configured window of the phase's timestamp. The round is marked as
+// fallback-priced when either feed had to use the fallback source.
+fn round_prices(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    round: &Round,
+    phase: PricePhase,
+) -> Result<RoundPrices, ContractError> {
+    let target = match phase {
+        PricePhase::Lock => round.lock_timestamp,
+        PricePhase::Close => round.close_timestamp,
+    };
+    let (feed_id, feed_id_b) = round_feed_ids(round, config);
+    let (info, mut origin) = fetch_price(deps, env, config, &feed_id, Some((round.epoch, phase)))?;
+    check_price_window(config, &info, target)?;
+
+    let price_b = match feed_id_b {
+        Some(feed_id_b) => {
+            let (info_b, origin_b) = fetch_price(deps, env, config, &feed_id_b, Some((round.epoch, phase)))?;
+            check_price_window(config, &info_b, target)?;
+            if origin_b == PriceOrigin::Fallback {
+                origin = origin_b;
+            }
+            Some(info_b.price)
+        }
+        None => None,
+    };
+
+    Ok(RoundPrices { info, price_b, origin })
+}
+
+// Keeps the operator from choosing the reading by timing the transaction
+fn check_price_window(config: &Config, info: &PriceInfo, target: u64) -> Result<(), ContractError> {
+    if info.publish_time.abs_diff(target) > config.price_window_seconds {
+        return Err(ContractError::PriceOutsideWindow {
+            publish_time: info.publish_time,
+            target,
+        });
+    }
+
+    Ok(())
+}
+
+// Price used to lock and settle rounds and to resolve fixed-strike markets: the median
+// of every configured source, or the Pyth oracle at oracle_address when none are set.
+// A stale or unreadable source hands over to the fallback; disagreeing sources do not.
+// `round` is the epoch and phase being priced, which Attested sources need; markets pass None.
+fn fetch_price(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    price_feed_id: &str,
+    round: Option<(u64, PricePhase)>,
+) -> Result<(PriceInfo, PriceOrigin), ContractError> {
+    let primary = if config.price_sources.is_empty() {
+        oracle_price(deps, env, config, price_feed_id)
+    } else {
+        median_price(deps, env, config, price_feed_id, round).map(|price| (price, PriceOrigin::Primary))
+    };
+
+    match primary {
+        Ok(price) => Ok(price),
+        Err(err @ ContractError::PriceDeviation { .. }) => Err(err),
+        Err(err) => match &config.fallback_price_source {
+            Some(source) => Ok((source_price(deps, env, source, price_feed_id, round)?, PriceOrigin::Fallback)),
+            None => Err(err),
+        },
+    }
+}
+
+// The configured Pyth oracle, used when no price sources are set. Mock-oracle builds price
+// from SetMockPrice instead once one is set.
+fn oracle_price(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    price_feed_id: &str,
+) -> Result<(PriceInfo, PriceOrigin), ContractError> {
+    #[cfg(feature = "mock-oracle")]
+    if let Some(price) = MOCK_PRICE.may_load(deps.storage)? {
+        let mock_price = PriceInfo {
+            price,
+            expo: 0,
+            conf: 0,
+            publish_time: env.block.time.seconds(),
+        };
+        return Ok((mock_price, PriceOrigin::Mock));
+    }
+
+    let price = query_price(
+        &deps.querier,
+        &config.oracle_address,
+        price_feed_id,
+        env.block.time.seconds(),
+        ORACLE_TIME_LIMIT,
+    )?;
+    Ok((price, PriceOrigin::Oracle))
+}
+
+fn median_price(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    price_feed_id: &str,
+    round: Option<(u64, PricePhase)>,
+) -> Result<PriceInfo, ContractError> {
+    let mut prices = config
+        .price_sources
+        .iter()
+        .map(|source| source_price(deps, env, source, price_feed_id, round))
+        .collect::<Result<Vec<PriceInfo>, ContractError>>()?;
+    prices.sort_unstable_by_key(|info| info.price);
+
+    // Prices are only comparable when every source reports the same exponent
+    if prices.iter().any(|info| info.expo != prices[0].expo) {
+        return Err(ContractError::OracleError("Price sources report different exponents".to_string()));
+    }
+
+    // Readings too large to average or compare can't be trusted to settle a round
+    let overflow = || ContractError::OracleError("Price sources overflow".to_string());
+
+    // An even count averages the middle pair, keeping the wider confidence and older publish time
+    let mid = prices.len() / 2;
+    let median = if prices.len() % 2 == 0 {
+        let (low, high) = (&prices[mid - 1], &prices[mid]);
+        PriceInfo {
+            price: low.price.checked_add(high.price).ok_or_else(overflow)? / 2,
+            expo: low.expo,
+            conf: low.conf.max(high.conf),
+            publish_time: low.publish_time.min(high.publish_time),
+        }
+    } else {
+        prices[mid].clone()
+    };
+    if median.price <= 0 {
+        return Err(ContractError::OracleError("Median price is not positive".to_string()));
+    }
+
+    let spread = prices[prices.len() - 1].price.abs_diff(prices[0].price);
+    let deviation_bps = spread.checked_mul(10000).ok_or_else(overflow)? / median.price.unsigned_abs();
+    if deviation_bps > u128::from(config.max_deviation_bps) {
+        return Err(ContractError::PriceDeviation {
+            deviation_bps,
+            max_deviation_bps: config.max_deviation_bps,
+        });
+    }
+
+    Ok(median)
+}
+
+fn price_origin_str(origin: &PriceOrigin) -> &'static str {
+    match origin {
+        #[cfg(feature = "mock-oracle")]
+        PriceOrigin::Mock => "mock",
+        PriceOrigin::Primary => "primary",
+        PriceOrigin::Fallback => "fallback",
+        PriceOrigin::Oracle => "oracle",
+    }
+}
+
+fn source_price(
+    deps: Deps,
+    env: &Env,
+    source: &PriceSource,
+    price_feed_id: &str,
+    round: Option<(u64, PricePhase)>,
+) -> Result<PriceInfo, ContractError> {
+    match source {
+        PriceSource::Pyth { oracle_address } => query_price(
+            &deps.querier,
+            oracle_address,
+            price_feed_id,
+            env.block.time.seconds(),
+            ORACLE_TIME_LIMIT,
+        ),
+        PriceSource::Feeder { address } => {
+            let fed_price = FED_PRICES
+                .may_load(deps.storage, (price_feed_id.to_string(), address.clone()))?
+                .ok_or_else(|| ContractError::OracleError(format!("No price from feeder {}", address)))?;
+            if env.block.time.seconds() > fed_price.publish_time + ORACLE_TIME_LIMIT {
+                return Err(ContractError::OracleError(format!("Price from feeder {} is too stale", address)));
+            }
+
+            Ok(PriceInfo {
+                price: fed_price.price,
+                expo: fed_price.expo,
+                conf: 0,
+                publish_time: fed_price.publish_time,
+            })
+        }
+        PriceSource::Attested { public_key, .. } => {
+            let (epoch, phase) = round.ok_or_else(|| ContractError::OracleError("Attested prices only cover rounds".to_string()))?;
+            attested_prices(phase)
+                .may_load(deps.storage, (price_feed_id.to_string(), epoch, public_key.to_vec()))?
+                .ok_or_else(|| ContractError::OracleError(format!("No attested price for round {}", epoch)))
+        }
+        PriceSource::OsmosisTwap { pools, window_seconds, expo } => {
+            let pool = pools
+                .iter()
+                .find(|pool| pool.price_feed_id == price_feed_id)
+                .ok_or_else(|| ContractError::OracleError(format!("No Osmosis pool for feed {}", price_feed_id)))?;
+
+            get_twap_price(
+                deps,
+                env.clone(),
+                pool.pool_id,
+                pool.base_asset.clone(),
+                pool.quote_asset.clone(),
+                *window_seconds,
+                *expo,
+            )
+        }
+    }
+}
+
+fn execute_submit_price(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    price_feed_id: String,
+    price: Uint128,
+    expo: i32,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let is_feeder = config
+        .price_sources
+        .iter()
+        .chain(config.fallback_price_source.iter())
+        .any(|source| matches!(source, PriceSource::Feeder { address } if *address == info.sender));
+    if !is_feeder {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if price.is_zero() {
+        return Err(ContractError::OracleError("Price must be positive".to_string()));
+    }
+    let price = i128::try_from(price.u128()).map_err(|_| ContractError::OracleError("Price is too large".to_string()))?;
+
+    FED_PRICES.save(deps.storage, (price_feed_id.clone(), info.sender.clone()), &FedPrice {
+        price,
+        expo,
+        publish_time: env.block.time.seconds(),
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "submit_price")
+        .add_attribute("feeder", info.sender)
+        .add_attribute("price_feed_id", price_feed_id)
+        .add_attribute("price", price.to_string())
+        .add_attribute("expo", expo.to_string()))
+}
+
 fn execute_genesis_start_round(
     mut deps: DepsMut,
     env: Env,
@@ -487,6 +3063,7 @@ fn execute_genesis_lock_round(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    attestations: Vec<PriceAttestation>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     if info.sender != config.operator_address {
@@ -498,12 +3075,26 @@ fn execute_genesis_lock_round(
         return Err(ContractError::Paused {});
     }
 
+    if settlement_halted(deps.storage)? {
+        return Err(ContractError::SettlementHalted {});
+    }
+
     let current_epoch = CURRENT_EPOCH.load(deps.storage)?;
     if current_epoch != 1 {
         return Err(ContractError::GenesisNotStarted {});
     }
 
+    let round = ROUNDS.load(deps.storage, current_epoch)?;
+    if is_locked(&round) || env.block.time.seconds() < round.lock_timestamp {
+        return Err(ContractError::RoundNotLockable { epoch: current_epoch });
+    }
+
+    // Attested sources price the genesis lock like any other, from the attestations passed in
+    let attested_keys = record_attestations(deps.branch(), &env, &config, attestations)?;
     let response = execute_lock_round(&mut deps, env, 1, &config)?;
+    for (phase, key) in attested_keys {
+        attested_prices(phase).remove(deps.storage, key);
+    }
 
     Ok(response)
 }
@@ -550,9 +3141,31 @@ fn execute_unpause(
         .add_attribute("admin", info.sender))
 }
 
+// Lets rounds and markets settle again after a circuit breaker trip has been reviewed
+fn execute_resume_settlement(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if !settlement_halted(deps.storage)? {
+        return Err(ContractError::SettlementNotHalted {});
+    }
+
+    SETTLEMENT_HALTED.save(deps.storage, &false)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "resume_settlement")
+        .add_attribute("admin", info.sender))
+}
+
+// Sweeps the treasury, first releasing a bounded page of pending fees whose claims have opened
 fn execute_claim_treasury(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
@@ -560,6 +3173,7 @@ fn execute_claim_treasury(
         return Err(ContractError::Unauthorized {});
     }
 
+    release_pending_fees(deps.storage, &env)?;
     let treasury = TREASURY.load(deps.storage)?;
     if treasury == Uint128::zero() {
         return Err(ContractError::NoTreasury {});
@@ -567,14 +3181,7 @@ fn execute_claim_treasury(
 
     TREASURY.save(deps.storage, &Uint128::zero())?;
 
-    let transfer_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: config.usdc_token.to_string(),
-        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: config.admin_address.to_string(),
-            amount: treasury, 
-        })?,
-        funds: vec![],
-    });
+    let transfer_msg = transfer_msg(&config, &config.admin_address, treasury)?;
 
     Ok(Response::new()
         .add_submessage(SubMsg::new(transfer_msg))
@@ -688,7 +3295,7 @@ fn execute_set_oracle_info(
 
     let oracle_addr = deps.api.addr_validate(&oracle_address)?;
 
-    if PriceIdentifier::from_hex(&btc_price_feed_id).is_err() {
+    if !is_valid_feed_id(&btc_price_feed_id) {
         return Err(ContractError::OracleError("Invalid price feed ID format".to_string()));
     }
 
@@ -702,15 +3309,405 @@ fn execute_set_oracle_info(
         .add_attribute("btc_price_feed_id", btc_price_feed_id))
 }
 
+fn execute_set_push_payouts(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.push_payouts_enabled = enabled;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_push_payouts")
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+fn execute_set_cancel_window(
+    deps: DepsMut,
+    info: MessageInfo,
+    cancel_window: Option<CancelWindow>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(window) = &cancel_window {
+        if window.fee_bps > 1000 {
+            return Err(ContractError::InvalidCancelFee {});
+        }
+    }
+
+    config.cancel_window = cancel_window.clone();
+    CONFIG.save(deps.storage, &config)?;
+
+    let mut response = Response::new()
+        .add_attribute("method", "set_cancel_window")
+        .add_attribute("enabled", cancel_window.is_some().to_string());
+    if let Some(window) = cancel_window {
+        response = response
+            .add_attribute("cutoff_seconds", window.cutoff_seconds.to_string())
+            .add_attribute("fee_bps", window.fee_bps.to_string());
+    }
+
+    Ok(response)
+}
+
+fn execute_set_settlement_challenge(
+    deps: DepsMut,
+    info: MessageInfo,
+    settlement_challenge: Option<SettlementChallenge>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(challenge) = &settlement_challenge {
+        if challenge.window_seconds == 0 || challenge.bond_amount.is_zero() {
+            return Err(ContractError::InvalidSettlementChallenge {});
+        }
+    }
+
+    config.settlement_challenge = settlement_challenge.clone();
+    CONFIG.save(deps.storage, &config)?;
+
+    let mut response = Response::new()
+        .add_attribute("method", "set_settlement_challenge")
+        .add_attribute("enabled", settlement_challenge.is_some().to_string());
+    if let Some(challenge) = settlement_challenge {
+        response = response
+            .add_attribute("window_seconds", challenge.window_seconds.to_string())
+            .add_attribute("bond_amount", challenge.bond_amount.to_string());
+    }
+
+    Ok(response)
+}
+
+fn execute_set_price_sources(
+    deps: DepsMut,
+    info: MessageInfo,
+    price_sources: Vec<PriceSource>,
+    max_deviation_bps: u64,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if price_sources.len() > MAX_PRICE_SOURCES || max_deviation_bps > 10000 {
+        return Err(ContractError::InvalidPriceSources {});
+    }
+    // A zero tolerance would cancel every round the sources don't price identically
+    if !price_sources.is_empty() && max_deviation_bps == 0 {
+        return Err(ContractError::InvalidPriceSources {});
+    }
+    for (i, source) in price_sources.iter().enumerate() {
+        if price_sources[..i].contains(source) {
+            return Err(ContractError::InvalidPriceSources {});
+        }
+        validate_price_source(deps.as_ref(), source)?;
+    }
+
+    config.price_sources = price_sources;
+    config.max_deviation_bps = max_deviation_bps;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_price_sources")
+        .add_attribute("sources", config.price_sources.len().to_string())
+        .add_attribute("max_deviation_bps", max_deviation_bps.to_string()))
+}
+
+fn execute_set_fallback_price_source(
+    deps: DepsMut,
+    info: MessageInfo,
+    fallback_price_source: Option<PriceSource>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(source) = &fallback_price_source {
+        validate_price_source(deps.as_ref(), source)?;
+    }
+
+    config.fallback_price_source = fallback_price_source;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_fallback_price_source")
+        .add_attribute("enabled", config.fallback_price_source.is_some().to_string()))
+}
+
+fn execute_set_price_window(
+    deps: DepsMut,
+    info: MessageInfo,
+    price_window_seconds: u64,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if price_window_seconds == 0 {
+        return Err(ContractError::InvalidPriceWindow {});
+    }
+
+    config.price_window_seconds = price_window_seconds;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_price_window")
+        .add_attribute("price_window_seconds", price_window_seconds.to_string()))
+}
+
+#[cfg(feature = "mock-oracle")]
+fn execute_set_mock_price(
+    deps: DepsMut,
+    info: MessageInfo,
+    price: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if price.is_zero() {
+        return Err(ContractError::OracleError("Price must be positive".to_string()));
+    }
+    let price = i128::try_from(price.u128()).map_err(|_| ContractError::OracleError("Price is too large".to_string()))?;
+    MOCK_PRICE.save(deps.storage, &price)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_mock_price")
+        .add_attribute("price", price.to_string()))
+}
+
+fn execute_set_circuit_breaker(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_price_move_bps: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if max_price_move_bps == Some(0) {
+        return Err(ContractError::InvalidCircuitBreaker {});
+    }
+
+    config.max_price_move_bps = max_price_move_bps;
+    CONFIG.save(deps.storage, &config)?;
+
+    let mut response = Response::new()
+        .add_attribute("method", "set_circuit_breaker")
+        .add_attribute("enabled", max_price_move_bps.is_some().to_string());
+    if let Some(max_price_move_bps) = max_price_move_bps {
+        response = response.add_attribute("max_price_move_bps", max_price_move_bps.to_string());
+    }
+
+    Ok(response)
+}
+
+fn validate_price_source(deps: Deps, source: &PriceSource) -> Result<(), ContractError> {
+    match source {
+        PriceSource::Pyth { oracle_address } => {
+            deps.api.addr_validate(oracle_address.as_str())?;
+        }
+        PriceSource::Feeder { address } => {
+            deps.api.addr_validate(address.as_str())?;
+        }
+        PriceSource::Attested { public_key, scheme } => {
+            if !is_valid_public_key(scheme, public_key) {
+                return Err(ContractError::InvalidPriceSources {});
+            }
+        }
+        PriceSource::OsmosisTwap { pools, window_seconds, expo } => {
+            if pools.is_empty() || *window_seconds == 0 || !(-18..=0).contains(expo) {
+                return Err(ContractError::InvalidPriceSources {});
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn execute_set_exposure_caps(
+    deps: DepsMut,
+    info: MessageInfo,
+    exposure_caps: ExposureCaps,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(max_bet_amount) = exposure_caps.max_bet_amount {
+        if max_bet_amount < config.min_bet_amount {
+            return Err(ContractError::InvalidExposureCaps {});
+        }
+    }
+    if let Some(max_imbalance_ratio) = exposure_caps.max_imbalance_ratio {
+        if max_imbalance_ratio < Decimal::one() {
+            return Err(ContractError::InvalidExposureCaps {});
+        }
+    }
+
+    config.exposure_caps = exposure_caps;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_exposure_caps"))
+}
+
+fn execute_set_proposer(
+    deps: DepsMut,
+    info: MessageInfo,
+    proposer_address: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.proposer_address = proposer_address
+        .as_ref()
+        .map(|addr| deps.api.addr_validate(addr))
+        .transpose()?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_proposer")
+        .add_attribute("proposer", proposer_address.unwrap_or_else(|| "none".to_string())))
+}
+
+fn execute_set_guardian(
+    deps: DepsMut,
+    info: MessageInfo,
+    guardian_address: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.guardian_address = guardian_address
+        .as_ref()
+        .map(|addr| deps.api.addr_validate(addr))
+        .transpose()?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_guardian")
+        .add_attribute("guardian", guardian_address.unwrap_or_else(|| "none".to_string())))
+}
+
+fn execute_set_market_kind(
+    deps: DepsMut,
+    info: MessageInfo,
+    market_kind: MarketKind,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let kind_str = match &market_kind {
+        MarketKind::UpDown => "up_down",
+        MarketKind::Range { boundaries_bps } => {
+            if boundaries_bps.is_empty() || boundaries_bps.len() >= MAX_RANGE_BUCKETS {
+                return Err(ContractError::InvalidMarketKind {});
+            }
+            if boundaries_bps.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(ContractError::InvalidMarketKind {});
+            }
+            "range"
+        }
+        MarketKind::StrikeOffset { offset } => {
+            let valid = match offset {
+                StrikeOffset::Bps(bps) => *bps > 0 && *bps <= 10000,
+                StrikeOffset::Absolute(amount) => !amount.is_zero(),
+            };
+            if !valid {
+                return Err(ContractError::InvalidMarketKind {});
+            }
+            "strike_offset"
+        }
+        MarketKind::Relative { feed_id_a, feed_id_b } => {
+            if !is_valid_feed_id(feed_id_a) || !is_valid_feed_id(feed_id_b) {
+                return Err(ContractError::OracleError("Invalid price feed ID format".to_string()));
+            }
+            if feed_id_a == feed_id_b {
+                return Err(ContractError::InvalidMarketKind {});
+            }
+            "relative"
+        }
+        MarketKind::Volatility { threshold_bps } => {
+            if *threshold_bps == 0 || *threshold_bps > 10000 {
+                return Err(ContractError::InvalidMarketKind {});
+            }
+            "volatility"
+        }
+    };
+
+    // Takes effect from the next round; running rounds keep the kind they started with
+    config.market_kind = market_kind;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_market_kind")
+        .add_attribute("market_kind", kind_str))
+}
+
+fn execute_set_flat_band(
+    deps: DepsMut,
+    info: MessageInfo,
+    flat_band_bps: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin_address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(band) = flat_band_bps {
+        if band == 0 || band > 10000 {
+            return Err(ContractError::InvalidFlatBand {});
+        }
+    }
+
+    config.flat_band_bps = flat_band_bps;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_flat_band")
+        .add_attribute("flat_band_bps", flat_band_bps.map_or("none".to_string(), |band| band.to_string())))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetRound { epoch } => to_json_binary(&query_round(deps, epoch)?),
         QueryMsg::GetCurrentEpoch {} => to_json_binary(&query_current_epoch(deps)?),
         QueryMsg::GetUserRounds { user, cursor, size } => to_json_binary(&query_user_rounds(deps, user, cursor, size)?),
-        QueryMsg::Claimable { epoch, user } => to_json_binary(&query_claimable(deps, epoch, user)?),
-        QueryMsg::Refundable { epoch, user } => to_json_binary(&query_refundable(deps, epoch, user)?),
+        QueryMsg::Claimable { epoch, user } => to_json_binary(&query_claimable(deps, env, epoch, user)?),
+        QueryMsg::Refundable { epoch, user } => to_json_binary(&query_refundable(deps, env, epoch, user)?),
         QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::GetBalance { user } => to_json_binary(&query_balance(deps, user)?),
+        QueryMsg::GetAutoRoll { user } => to_json_binary(&query_auto_roll(deps, user)?),
+        QueryMsg::GetMarket { market_id } => to_json_binary(&MARKETS.load(deps.storage, market_id)?),
+        QueryMsg::GetMarketBet { market_id, user } => to_json_binary(&query_market_bet(deps, market_id, user)?),
+        QueryMsg::GetStandingOrders { user } => to_json_binary(&query_standing_orders(deps, user)?),
+        QueryMsg::GetChallenge { epoch } => to_json_binary(&ChallengeResponse {
+            challenge: CHALLENGES.may_load(deps.storage, epoch)?,
+        }),
     }
 }
 
@@ -718,17 +3715,32 @@ fn query_round(deps: Deps, epoch: u64) -> StdResult<RoundResponse> {
     let round = ROUNDS.load(deps.storage, epoch)?;
     Ok(RoundResponse {
         epoch: round.epoch,
+        kind: round.kind,
         start_timestamp: round.start_timestamp,
         lock_timestamp: round.lock_timestamp,
         close_timestamp: round.close_timestamp,
         lock_price: round.lock_price,
         close_price: round.close_price,
+        lock_price_b: round.lock_price_b,
+        close_price_b: round.close_price_b,
+        lock_price_info: round.lock_price_info,
+        close_price_info: round.close_price_info,
+        lock_price_origin: round.lock_price_origin,
+        close_price_origin: round.close_price_origin,
         total_amount: round.total_amount,
         bull_amount: round.bull_amount,
         bear_amount: round.bear_amount,
+        flat_amount: round.flat_amount,
+        calm_amount: round.calm_amount,
+        volatile_amount: round.volatile_amount,
+        flat_band_bps: round.flat_band_bps,
+        bucket_amounts: round.bucket_amounts,
         reward_base_amount: round.reward_base_amount,
         reward_amount: round.reward_amount,
         oracle_called: round.oracle_called,
+        claims_open_at: round.claims_open_at,
+        cancelled: round.cancelled,
+        flagged: round.flagged,
     })
 }
 
@@ -767,13 +3779,14 @@ fn query_user_rounds(
 // Query function to check if a round is claimable for a user
 fn query_claimable(
     deps: Deps,
+    env: Env,
     epoch: u64,
     user: String,
 ) -> StdResult<ClaimableResponse> {
     let user_addr = deps.api.addr_validate(&user)?;
     
     let round = ROUNDS.load(deps.storage, epoch)?;
-    if !round.oracle_called {
+    if !round.oracle_called || !claims_open(deps.storage, &env, &round)? {
         return Ok(ClaimableResponse {
             is_claimable: false,
             position: None,
@@ -817,6 +3830,7 @@ fn query_claimable(
 
 fn query_refundable(
     deps: Deps,
+    env: Env,
     epoch: u64,
     user: String,
 ) -> StdResult<RefundableResponse> {
@@ -839,17 +3853,18 @@ fn query_refundable(
         });
     }
     
-    let is_refundable = round.oracle_called && round.lock_price == round.close_price;
+    let is_refundable = round.oracle_called && claims_open(deps.storage, &env, &round)? && is_refund_round(&round);
     
     Ok(RefundableResponse {
         is_refundable,
-        amount: if is_refundable { Some(bet_info.amount) } else { None },
+        amount: if is_refundable { Some(refund_amount(&round, &bet_info)) } else { None },
     })
 }
 
 fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
     let paused = PAUSED.load(deps.storage)?;
+    let settlement_halted = settlement_halted(deps.storage)?;
     
     Ok(ConfigResponse {
         usdc_token: config.usdc_token.to_string(),
@@ -861,6 +3876,55 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         treasury_fee: config.treasury_fee,
         oracle_address: config.oracle_address.to_string(),
         btc_price_feed_id: config.btc_price_feed_id,
+        push_payouts_enabled: config.push_payouts_enabled,
+        proposer_address: config.proposer_address.map(|addr| addr.to_string()),
+        market_kind: config.market_kind,
+        flat_band_bps: config.flat_band_bps,
+        cancel_window: config.cancel_window,
+        exposure_caps: config.exposure_caps,
+        settlement_challenge: config.settlement_challenge,
+        price_sources: config.price_sources,
+        max_deviation_bps: config.max_deviation_bps,
+        fallback_price_source: config.fallback_price_source,
+        price_window_seconds: config.price_window_seconds,
+        max_price_move_bps: config.max_price_move_bps,
+        guardian_address: config.guardian_address.map(|addr| addr.to_string()),
         paused,
+        settlement_halted,
     })
-}
\ No newline at end of file
+}
+
+fn query_balance(deps: Deps, user: String) -> StdResult<BalanceResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let balance = BALANCES.may_load(deps.storage, user_addr)?.unwrap_or_default();
+
+    Ok(BalanceResponse { balance })
+}
+
+fn query_auto_roll(deps: Deps, user: String) -> StdResult<AutoRollResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let auto_roll = AUTO_ROLL.may_load(deps.storage, user_addr)?;
+
+    Ok(AutoRollResponse { auto_roll })
+}
+
+fn query_standing_orders(deps: Deps, user: String) -> StdResult<StandingOrdersResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let orders = OWNER_STANDING_ORDERS
+        .prefix(user_addr)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|id| STANDING_ORDERS.load(deps.storage, id?))
+        .collect::<StdResult<Vec<StandingOrder>>>()?;
+
+    Ok(StandingOrdersResponse { orders })
+}
+
+fn query_market_bet(deps: Deps, market_id: u64, user: String) -> StdResult<MarketBetResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let bet = MARKET_LEDGER.may_load(deps.storage, (market_id, user_addr))?;
+
+    Ok(MarketBetResponse { bet })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,1599 @@
+use std::collections::HashMap;
+
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{coin, Coin, ContractResult, OwnedDeps, SystemError, SystemResult, Timestamp, WasmQuery};
+use p256::ecdsa::signature::hazmat::PrehashSigner;
+use p256::ecdsa::{Signature, SigningKey};
+use pyth_sdk_cw::{Price, PriceFeed, PriceIdentifier};
+use serde_json::{json, Value};
+
+use super::*;
+use crate::state::{SignatureScheme, TwapPool, ATTESTED_CLOSE_PRICES, ATTESTED_LOCK_PRICES, LEGACY_EPOCH};
+
+type TestDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+const ADMIN: &str = "admin";
+const OPERATOR: &str = "operator";
+const USDC: &str = "usdc";
+const PYTH: &str = "pyth";
+const ALICE: &str = "alice";
+const BOB: &str = "bob";
+const CAROL: &str = "carol";
+const DAVE: &str = "dave";
+const BTC_FEED: &str = "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43";
+const ETH_FEED: &str = "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace";
+const GENESIS: u64 = 1_700_000_000;
+
+// Epoch n locks 270n seconds after genesis and closes 30 seconds later
+fn lock_time(epoch: u64) -> u64 {
+    GENESIS + 270 * epoch
+}
+
+fn close_time(epoch: u64) -> u64 {
+    lock_time(epoch) + 30
+}
+
+// A time the epoch is open for bets
+fn open_time(epoch: u64) -> u64 {
+    lock_time(epoch) - 100
+}
+
+fn at(time: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(time);
+    env
+}
+
+fn exec(deps: &mut TestDeps, time: u64, sender: &str, msg: ExecuteMsg) -> Result<Response, ContractError> {
+    execute(deps.as_mut(), at(time), mock_info(sender, &[]), msg)
+}
+
+fn exec_with_funds(
+    deps: &mut TestDeps,
+    time: u64,
+    sender: &str,
+    funds: &[Coin],
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    execute(deps.as_mut(), at(time), mock_info(sender, funds), msg)
+}
+
+// 300 second rounds with a 30 second buffer and a 3% fee; every bettor starts with 1000 of balance
+fn setup() -> TestDeps {
+    let mut deps = mock_dependencies();
+    instantiate(deps.as_mut(), at(GENESIS), mock_info(ADMIN, &[]), InstantiateMsg {
+        admin_address: ADMIN.to_string(),
+        operator_address: OPERATOR.to_string(),
+        usdc_token: USDC.to_string(),
+        interval_seconds: 300,
+        buffer_seconds: 30,
+        min_bet_amount: Uint128::new(10),
+        treasury_fee: 300,
+        oracle_address: PYTH.to_string(),
+        btc_price_feed_id: BTC_FEED.to_string(),
+    })
+    .unwrap();
+
+    for user in [ALICE, BOB, CAROL, DAVE] {
+        exec_with_funds(&mut deps, GENESIS, user, &[coin(1000, USDC)], ExecuteMsg::Deposit {}).unwrap();
+    }
+    deps
+}
+
+// Serves `(feed id, price, conf, publish_time)` readings with expo -2 from the Pyth contract,
+// and a 1uatom update fee
+fn set_pyth(deps: &mut TestDeps, readings: &[(&str, i64, u64, u64)]) {
+    let feeds: HashMap<PriceIdentifier, Price> = readings
+        .iter()
+        .map(|&(feed_id, price, conf, publish_time)| {
+            let price = Price { price, conf, expo: -2, publish_time: publish_time as i64 };
+            (PriceIdentifier::from_hex(feed_id).unwrap(), price)
+        })
+        .collect();
+
+    deps.querier.update_wasm(move |query| {
+        let (contract_addr, msg) = match query {
+            WasmQuery::Smart { contract_addr, msg } => (contract_addr, msg),
+            _ => return SystemResult::Err(SystemError::UnsupportedRequest { kind: "wasm".to_string() }),
+        };
+        if contract_addr != PYTH {
+            return SystemResult::Err(SystemError::NoSuchContract { addr: contract_addr.clone() });
+        }
+
+        let msg: Value = from_json(msg).unwrap();
+        let reply = match msg.get("price_feed") {
+            Some(query) => {
+                let id: PriceIdentifier = serde_json::from_value(query["id"].clone()).unwrap();
+                match feeds.get(&id) {
+                    Some(price) => json!({ "price_feed": PriceFeed::new(id, *price, *price) }),
+                    None => return SystemResult::Ok(ContractResult::Err("price feed not found".to_string())),
+                }
+            }
+            None => json!({ "denom": "uatom", "amount": "1" }),
+        };
+        SystemResult::Ok(ContractResult::Ok(to_json_binary(&reply).unwrap()))
+    });
+}
+
+// Prices the BTC feed at `price`, published at `time`
+fn set_price(deps: &mut TestDeps, time: u64, price: i64) {
+    set_pyth(deps, &[(BTC_FEED, price, 0, time)]);
+}
+
+fn start_rounds(deps: &mut TestDeps) -> Response {
+    exec(deps, GENESIS, OPERATOR, ExecuteMsg::GenesisStartRound {}).unwrap()
+}
+
+// Locks epoch 1 at `price` and starts epoch 2
+fn lock_genesis(deps: &mut TestDeps, price: i64) {
+    set_price(deps, lock_time(1), price);
+    exec(deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+    exec(deps, lock_time(1), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+}
+
+fn run_round(deps: &mut TestDeps, time: u64, price: i64) -> Response {
+    set_price(deps, time, price);
+    exec(deps, time, OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap()
+}
+
+fn bet_msg(epoch: u64, position: Position, amount: u128) -> ExecuteMsg {
+    let amount = Uint128::new(amount);
+    match position {
+        Position::Bull => ExecuteMsg::BetBull { epoch, amount },
+        Position::Bear => ExecuteMsg::BetBear { epoch, amount },
+        Position::Flat => ExecuteMsg::BetFlat { epoch, amount },
+        Position::Calm => ExecuteMsg::BetCalm { epoch, amount },
+        Position::Volatile => ExecuteMsg::BetVolatile { epoch, amount },
+        Position::Bucket(bucket) => ExecuteMsg::BetBucket { epoch, amount, bucket },
+        Position::Yes | Position::No => panic!("rounds take no market positions"),
+    }
+}
+
+fn bet(deps: &mut TestDeps, user: &str, epoch: u64, position: Position, amount: u128) -> Result<Response, ContractError> {
+    exec(deps, open_time(epoch), user, bet_msg(epoch, position, amount))
+}
+
+// Epoch 1 with Alice on Bull and Bob on Bear for 100 each, settled with the price up
+fn settle_bull_round(deps: &mut TestDeps) {
+    start_rounds(deps);
+    bet(deps, ALICE, 1, Position::Bull, 100).unwrap();
+    bet(deps, BOB, 1, Position::Bear, 100).unwrap();
+    lock_genesis(deps, 6_000_000);
+    run_round(deps, close_time(1), 6_100_000);
+}
+
+fn claim(deps: &mut TestDeps, time: u64, user: &str, epoch: u64) -> Result<Response, ContractError> {
+    exec(deps, time, user, ExecuteMsg::Claim { epochs: vec![epoch] })
+}
+
+fn balance(deps: &TestDeps, user: &str) -> u128 {
+    BALANCES.may_load(&deps.storage, Addr::unchecked(user)).unwrap().unwrap_or_default().u128()
+}
+
+fn treasury(deps: &TestDeps) -> u128 {
+    TREASURY.load(&deps.storage).unwrap().u128()
+}
+
+fn load_round(deps: &TestDeps, epoch: u64) -> Round {
+    ROUNDS.load(&deps.storage, epoch).unwrap()
+}
+
+fn attr(res: &Response, key: &str) -> String {
+    res.attributes.iter().find(|attr| attr.key == key).unwrap().value.clone()
+}
+
+fn event_attr(res: &Response, ty: &str, key: &str) -> Option<String> {
+    res.events
+        .iter()
+        .find(|event| event.ty == ty)
+        .and_then(|event| event.attributes.iter().find(|attr| attr.key == key))
+        .map(|attr| attr.value.clone())
+}
+
+fn has_event(res: &Response, ty: &str) -> bool {
+    res.events.iter().any(|event| event.ty == ty)
+}
+
+fn set_challenge_window(deps: &mut TestDeps) {
+    exec(deps, GENESIS, ADMIN, ExecuteMsg::SetSettlementChallenge {
+        settlement_challenge: Some(SettlementChallenge {
+            window_seconds: 100,
+            bond_amount: Uint128::new(50),
+        }),
+    })
+    .unwrap();
+}
+
+fn challenge(deps: &mut TestDeps, time: u64, challenger: &str, close_price: u128) -> Result<Response, ContractError> {
+    exec_with_funds(deps, time, challenger, &[coin(1, "uatom")], ExecuteMsg::ChallengeSettlement {
+        epoch: 1,
+        close_price: Uint128::new(close_price),
+        close_price_b: None,
+        price_update: vec![Binary::from(b"vaa".to_vec())],
+    })
+}
+
+fn verify_challenge(deps: &mut TestDeps, time: u64) -> Response {
+    let contract = mock_env().contract.address;
+    exec(deps, time, contract.as_str(), ExecuteMsg::VerifyChallenge { epoch: 1 }).unwrap()
+}
+
+#[test]
+fn migrate_defaults_new_fields_and_rejects_other_contracts() {
+    let mut deps = setup();
+
+    // Config as stored by the first release
+    let legacy = json!({
+        "usdc_token": USDC,
+        "admin_address": ADMIN,
+        "operator_address": OPERATOR,
+        "interval_seconds": 300,
+        "buffer_seconds": 30,
+        "min_bet_amount": "10",
+        "treasury_fee": 300,
+        "oracle_address": PYTH,
+        "btc_price_feed_id": BTC_FEED,
+    });
+    deps.storage.set(b"config", &serde_json::to_vec(&legacy).unwrap());
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(config.price_window_seconds, 60);
+    assert_eq!(config.market_kind, MarketKind::UpDown);
+    assert!(config.price_sources.is_empty());
+
+    set_contract_version(&mut deps.storage, "crates.io:other", "1.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMigration { .. }));
+}
+
+#[test]
+fn migrate_leaves_rounds_of_the_first_release_behind() {
+    let mut deps = setup();
+    LEGACY_EPOCH.remove(&mut deps.storage);
+
+    // The first release took the fee at lock and only ever ended the current round, so
+    // round 2 was locked with its fee in the treasury and never ended
+    let legacy_round = |epoch: u64, total: u128, fee: u128, oracle_called: bool| -> Round {
+        from_json(json!({
+            "epoch": epoch,
+            "start_timestamp": GENESIS + 300 * (epoch - 1),
+            "lock_timestamp": GENESIS + 300 * epoch - 30,
+            "close_timestamp": GENESIS + 300 * epoch,
+            "lock_price": "6000000",
+            "close_price": if oracle_called { "6100000" } else { "0" },
+            "total_amount": total.to_string(),
+            "bull_amount": (total / 2).to_string(),
+            "bear_amount": (total / 2).to_string(),
+            "reward_base_amount": fee.to_string(),
+            "reward_amount": "0",
+            "oracle_called": oracle_called,
+        }).to_string().as_bytes()).unwrap()
+    };
+    ROUNDS.save(&mut deps.storage, 1, &legacy_round(1, 0, 0, true)).unwrap();
+    ROUNDS.save(&mut deps.storage, 2, &legacy_round(2, 200, 6, false)).unwrap();
+    ROUNDS.save(&mut deps.storage, 3, &legacy_round(3, 0, 0, false)).unwrap();
+    for (user, position) in [(ALICE, Position::Bull), (BOB, Position::Bear)] {
+        let bet_info = BetInfo { position, amount: Uint128::new(100), claimed: false };
+        LEDGER.save(&mut deps.storage, (2, Addr::unchecked(user)), &bet_info).unwrap();
+    }
+    CURRENT_EPOCH.save(&mut deps.storage, &3).unwrap();
+    TREASURY.save(&mut deps.storage, &Uint128::new(6)).unwrap();
+
+    let migrated_at = GENESIS + 1000;
+    let res = migrate(deps.as_mut(), at(migrated_at), MigrateMsg {}).unwrap();
+    assert_eq!(attr(&res, "legacy_epoch"), "3");
+    assert_eq!(CURRENT_EPOCH.load(&deps.storage).unwrap(), 4);
+    assert_eq!(load_round(&deps, 4).start_timestamp, migrated_at);
+
+    // ExecuteRound locks and ends the fresh round without walking back into legacy ones
+    set_price(&mut deps, migrated_at + 270, 6_000_000);
+    exec(&mut deps, migrated_at + 270, OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+    set_price(&mut deps, migrated_at + 300, 6_100_000);
+    exec(&mut deps, migrated_at + 300, OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+    assert!(load_round(&deps, 4).oracle_called);
+    for epoch in [2, 3] {
+        let round = load_round(&deps, epoch);
+        assert!(!round.oracle_called && !round.cancelled);
+    }
+    assert_eq!(load_round(&deps, 2).reward_base_amount, Uint128::new(6));
+    assert_eq!(treasury(&deps), 6);
+
+    // Cancelling the stuck round refunds the stakes less the fee already in the treasury
+    exec(&mut deps, migrated_at + 300, ADMIN, ExecuteMsg::CancelRound { epoch: 2, reason: "legacy".to_string() }).unwrap();
+    for user in [ALICE, BOB] {
+        claim(&mut deps, migrated_at + 300, user, 2).unwrap();
+        assert_eq!(balance(&deps, user), 1000 + 97);
+    }
+    assert_eq!(treasury(&deps), 6);
+
+    // A later migration leaves the cutoff alone
+    let res = migrate(deps.as_mut(), at(migrated_at + 400), MigrateMsg {}).unwrap();
+    assert!(res.attributes.iter().all(|attribute| attribute.key != "legacy_epoch"));
+}
+
+#[test]
+fn claim_for_sends_the_payout_to_the_bettor_whoever_calls_it() {
+    let mut deps = setup();
+    settle_bull_round(&mut deps);
+
+    let res = exec(&mut deps, close_time(1), CAROL, ExecuteMsg::ClaimFor { user: ALICE.to_string(), epochs: vec![1] }).unwrap();
+
+    // 200 staked, less the 3% fee, all to Alice
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(transfer_msg(&config, &Addr::unchecked(ALICE), Uint128::new(194)).unwrap())]);
+    assert_eq!(balance(&deps, ALICE), 900);
+    assert_eq!(balance(&deps, CAROL), 1000);
+
+    let err = exec(&mut deps, close_time(1), OPERATOR, ExecuteMsg::ClaimFor { user: ALICE.to_string(), epochs: vec![1] }).unwrap_err();
+    assert!(matches!(err, ContractError::AlreadyClaimed { epoch: 1 }));
+    let err = exec(&mut deps, close_time(1), OPERATOR, ExecuteMsg::ClaimFor { user: BOB.to_string(), epochs: vec![1] }).unwrap_err();
+    assert!(matches!(err, ContractError::NotWinner { epoch: 1 }));
+}
+
+#[test]
+fn claim_for_keeps_only_the_auto_rolled_part_in_the_contract() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ALICE, ExecuteMsg::SetAutoRoll {
+        position_strategy: RollStrategy::SameSide,
+        fraction: Decimal::percent(50),
+    })
+    .unwrap();
+    settle_bull_round(&mut deps);
+
+    let res = exec(&mut deps, close_time(1), OPERATOR, ExecuteMsg::ClaimFor { user: ALICE.to_string(), epochs: vec![1] }).unwrap();
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(transfer_msg(&config, &Addr::unchecked(ALICE), Uint128::new(97)).unwrap())]);
+    assert_eq!(LEDGER.load(&deps.storage, (2, Addr::unchecked(ALICE))).unwrap().amount, Uint128::new(97));
+    assert_eq!(balance(&deps, ALICE), 900);
+}
+
+#[test]
+fn push_payouts_pay_unclaimed_winners_in_pages() {
+    let mut deps = setup();
+    settle_bull_round(&mut deps);
+
+    let push = |start_after: Option<&str>| ExecuteMsg::PushPayouts {
+        epoch: 1,
+        start_after: start_after.map(|user| user.to_string()),
+        limit: Some(1),
+    };
+    let err = exec(&mut deps, close_time(1), OPERATOR, push(None)).unwrap_err();
+    assert!(matches!(err, ContractError::PushPayoutsDisabled {}));
+
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPushPayouts { enabled: true }).unwrap();
+    let res = exec(&mut deps, close_time(1), OPERATOR, push(None)).unwrap();
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(transfer_msg(&config, &Addr::unchecked(ALICE), Uint128::new(194)).unwrap())]);
+    assert_eq!(attr(&res, "last_user"), ALICE);
+
+    let res = exec(&mut deps, close_time(1), OPERATOR, push(Some(ALICE))).unwrap();
+    assert!(res.messages.is_empty());
+
+    let err = claim(&mut deps, close_time(1), ALICE, 1).unwrap_err();
+    assert!(matches!(err, ContractError::AlreadyClaimed { epoch: 1 }));
+}
+
+#[test]
+fn deposits_fund_bets_and_withdrawals_pay_out_the_balance() {
+    let mut deps = setup();
+    start_rounds(&mut deps);
+
+    let err = exec_with_funds(&mut deps, GENESIS, ALICE, &[coin(10, "uatom")], ExecuteMsg::Deposit {}).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidBetFunds));
+
+    let res = bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    assert_eq!(attr(&res, "funding"), "balance");
+    assert_eq!(balance(&deps, ALICE), 900);
+
+    // Attached funds pay the bet directly
+    let res = exec_with_funds(&mut deps, open_time(1), ALICE, &[coin(50, USDC)], bet_msg(1, Position::Bull, 50)).unwrap();
+    assert_eq!(attr(&res, "funding"), "funds");
+    assert_eq!(balance(&deps, ALICE), 900);
+
+    let receive = |amount: u128| ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: CAROL.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_json_binary(&ReceiveMsg::Deposit {}).unwrap(),
+    });
+    let err = exec(&mut deps, GENESIS, "fake-token", receive(25)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidBetFunds));
+    exec(&mut deps, GENESIS, USDC, receive(25)).unwrap();
+    assert_eq!(balance(&deps, CAROL), 1025);
+
+    let err = exec(&mut deps, GENESIS, ALICE, ExecuteMsg::Withdraw { amount: Uint128::new(901) }).unwrap_err();
+    assert!(matches!(err, ContractError::InsufficientBalance {}));
+    let res = exec(&mut deps, GENESIS, ALICE, ExecuteMsg::Withdraw { amount: Uint128::new(900) }).unwrap();
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(transfer_msg(&config, &Addr::unchecked(ALICE), Uint128::new(900)).unwrap())]);
+    assert_eq!(balance(&deps, ALICE), 0);
+}
+
+#[test]
+fn auto_roll_reinvests_part_of_a_claim_into_the_open_round() {
+    let mut deps = setup();
+
+    let err = exec(&mut deps, GENESIS, ALICE, ExecuteMsg::SetAutoRoll {
+        position_strategy: RollStrategy::SameSide,
+        fraction: Decimal::percent(101),
+    })
+    .unwrap_err();
+    assert!(matches!(err, ContractError::InvalidRollFraction {}));
+
+    exec(&mut deps, GENESIS, ALICE, ExecuteMsg::SetAutoRoll {
+        position_strategy: RollStrategy::SameSide,
+        fraction: Decimal::percent(50),
+    })
+    .unwrap();
+    exec(&mut deps, GENESIS, BOB, ExecuteMsg::SetAutoRoll {
+        position_strategy: RollStrategy::Opposite,
+        fraction: Decimal::percent(50),
+    })
+    .unwrap();
+    settle_bull_round(&mut deps);
+
+    let res = claim(&mut deps, close_time(1), ALICE, 1).unwrap();
+    assert_eq!(event_attr(&res, "auto_roll", "amount").unwrap(), "97");
+    let rolled = LEDGER.load(&deps.storage, (2, Addr::unchecked(ALICE))).unwrap();
+    assert_eq!((rolled.position, rolled.amount), (Position::Bull, Uint128::new(97)));
+    assert_eq!(balance(&deps, ALICE), 900 + 194 - 97);
+}
+
+#[test]
+fn auto_roll_reinvests_winnings_but_not_refunds_claimed_alongside() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ALICE, ExecuteMsg::SetAutoRoll {
+        position_strategy: RollStrategy::SameSide,
+        fraction: Decimal::percent(50),
+    })
+    .unwrap();
+    settle_bull_round(&mut deps);
+
+    // Nobody takes the other side of epoch 2, so Alice's stake is refunded
+    bet(&mut deps, ALICE, 2, Position::Bull, 100).unwrap();
+    run_round(&mut deps, lock_time(2), 6_100_000);
+    run_round(&mut deps, close_time(2), 6_200_000);
+
+    let res = exec(&mut deps, close_time(2), ALICE, ExecuteMsg::Claim { epochs: vec![1, 2] }).unwrap();
+    assert_eq!(attr(&res, "total_reward"), "294");
+    assert_eq!(event_attr(&res, "auto_roll", "amount").unwrap(), "97");
+    assert_eq!(LEDGER.load(&deps.storage, (3, Addr::unchecked(ALICE))).unwrap().amount, Uint128::new(97));
+    assert_eq!(balance(&deps, ALICE), 800 + 294 - 97);
+}
+
+#[test]
+fn standing_orders_bet_from_escrow_as_rounds_start() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ALICE, ExecuteMsg::PlaceStandingOrder {
+        position: Position::Bull,
+        amount_per_round: Uint128::new(20),
+        rounds: 2,
+    })
+    .unwrap();
+    assert_eq!(balance(&deps, ALICE), 960);
+
+    start_rounds(&mut deps);
+    assert_eq!(LEDGER.load(&deps.storage, (1, Addr::unchecked(ALICE))).unwrap().amount, Uint128::new(20));
+    let order = STANDING_ORDERS.load(&deps.storage, 1).unwrap();
+    assert_eq!((order.rounds_remaining, order.escrow), (1, Uint128::new(20)));
+
+    lock_genesis(&mut deps, 6_000_000);
+    assert_eq!(LEDGER.load(&deps.storage, (2, Addr::unchecked(ALICE))).unwrap().amount, Uint128::new(20));
+    assert!(!STANDING_ORDERS.has(&deps.storage, 1));
+    assert!(!OWNER_STANDING_ORDERS.has(&deps.storage, (Addr::unchecked(ALICE), 1)));
+}
+
+#[test]
+fn standing_orders_are_capped_per_owner_and_limited_to_offered_positions() {
+    let mut deps = setup();
+    let place = |position: Position| ExecuteMsg::PlaceStandingOrder {
+        position,
+        amount_per_round: Uint128::new(10),
+        rounds: 1,
+    };
+
+    let err = exec(&mut deps, GENESIS, ALICE, place(Position::Flat)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidOrderPosition {}));
+    let err = exec(&mut deps, GENESIS, ALICE, place(Position::Bucket(0))).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidOrderPosition {}));
+
+    for _ in 0..MAX_STANDING_ORDERS_PER_USER {
+        exec(&mut deps, GENESIS, ALICE, place(Position::Bull)).unwrap();
+    }
+    let err = exec(&mut deps, GENESIS, ALICE, place(Position::Bull)).unwrap_err();
+    assert!(matches!(err, ContractError::TooManyStandingOrders {}));
+    exec(&mut deps, GENESIS, BOB, place(Position::Bear)).unwrap();
+
+    let res = exec(&mut deps, GENESIS, ALICE, ExecuteMsg::CancelStandingOrder { id: 1 }).unwrap();
+    assert_eq!(attr(&res, "refund"), "10");
+    exec(&mut deps, GENESIS, ALICE, place(Position::Bull)).unwrap();
+
+    let orders = query_standing_orders(deps.as_ref(), ALICE.to_string()).unwrap().orders;
+    assert_eq!(orders.len(), MAX_STANDING_ORDERS_PER_USER);
+}
+
+#[test]
+fn standing_orders_fill_in_pages_and_a_failed_fill_refunds_the_round() {
+    let mut deps = setup();
+    let place = |position: Position| ExecuteMsg::PlaceStandingOrder {
+        position,
+        amount_per_round: Uint128::new(10),
+        rounds: 2,
+    };
+    for user in [ALICE, BOB] {
+        for _ in 0..10 {
+            exec(&mut deps, GENESIS, user, place(Position::Bull)).unwrap();
+        }
+    }
+    for _ in 0..4 {
+        exec(&mut deps, GENESIS, CAROL, place(Position::Bull)).unwrap();
+    }
+    // Can't fill once Carol's Bull orders have bet this round
+    exec(&mut deps, GENESIS, CAROL, place(Position::Bear)).unwrap();
+    assert_eq!(balance(&deps, CAROL), 900);
+
+    let res = start_rounds(&mut deps);
+    assert_eq!(attr(&res, "standing_orders_done"), "false");
+    assert_eq!(load_round(&deps, 1).total_amount, Uint128::new(200));
+
+    let fill = ExecuteMsg::FillStandingOrders { limit: None };
+    let err = exec(&mut deps, GENESIS + 1, ALICE, fill.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let res = exec(&mut deps, GENESIS + 1, OPERATOR, fill.clone()).unwrap();
+    assert_eq!(attr(&res, "done"), "true");
+    assert_eq!(event_attr(&res, "standing_order_skipped", "id").unwrap(), "25");
+    assert_eq!(load_round(&deps, 1).total_amount, Uint128::new(240));
+    assert_eq!(balance(&deps, CAROL), 910);
+    let skipped = STANDING_ORDERS.load(&deps.storage, 25).unwrap();
+    assert_eq!((skipped.rounds_remaining, skipped.escrow), (1, Uint128::new(10)));
+
+    // Every order has been visited for this epoch
+    let res = exec(&mut deps, GENESIS + 2, OPERATOR, fill).unwrap();
+    assert!(res.events.is_empty());
+    assert_eq!(load_round(&deps, 1).total_amount, Uint128::new(240));
+}
+
+#[test]
+fn bets_top_up_the_same_side_until_lock() {
+    let mut deps = setup();
+    start_rounds(&mut deps);
+
+    bet(&mut deps, ALICE, 1, Position::Bull, 50).unwrap();
+    let res = bet(&mut deps, ALICE, 1, Position::Bull, 30).unwrap();
+    assert_eq!(attr(&res, "stake"), "80");
+    assert_eq!(load_round(&deps, 1).bull_amount, Uint128::new(80));
+
+    let err = bet(&mut deps, ALICE, 1, Position::Bear, 30).unwrap_err();
+    assert!(matches!(err, ContractError::PositionMismatch {}));
+    let err = exec(&mut deps, lock_time(1), ALICE, bet_msg(1, Position::Bull, 30)).unwrap_err();
+    assert!(matches!(err, ContractError::RoundNotBettable {}));
+}
+
+#[test]
+fn cancel_bet_refunds_the_stake_less_the_fee_before_the_cutoff() {
+    let mut deps = setup();
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+
+    let err = exec(&mut deps, open_time(1), ALICE, ExecuteMsg::CancelBet { epoch: 1 }).unwrap_err();
+    assert!(matches!(err, ContractError::CancelDisabled {}));
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetCancelWindow {
+        cancel_window: Some(CancelWindow { cutoff_seconds: 60, fee_bps: 1001 }),
+    })
+    .unwrap_err();
+    assert!(matches!(err, ContractError::InvalidCancelFee {}));
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetCancelWindow {
+        cancel_window: Some(CancelWindow { cutoff_seconds: 60, fee_bps: 1000 }),
+    })
+    .unwrap();
+
+    exec(&mut deps, lock_time(1) - 61, ALICE, ExecuteMsg::CancelBet { epoch: 1 }).unwrap();
+    assert_eq!(balance(&deps, ALICE), 990);
+    assert_eq!(treasury(&deps), 10);
+    assert!(load_round(&deps, 1).total_amount.is_zero());
+    assert!(!LEDGER.has(&deps.storage, (1, Addr::unchecked(ALICE))));
+
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    let err = exec(&mut deps, lock_time(1) - 60, ALICE, ExecuteMsg::CancelBet { epoch: 1 }).unwrap_err();
+    assert!(matches!(err, ContractError::CancelWindowClosed { epoch: 1 }));
+
+    exec(&mut deps, open_time(1), ADMIN, ExecuteMsg::CancelRound { epoch: 1, reason: "test".to_string() }).unwrap();
+    let err = exec(&mut deps, open_time(1), ALICE, ExecuteMsg::CancelBet { epoch: 1 }).unwrap_err();
+    assert!(matches!(err, ContractError::RoundCancelled { epoch: 1 }));
+}
+
+#[test]
+fn exposure_caps_limit_bets_users_rounds_and_imbalance() {
+    let mut deps = setup();
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetExposureCaps {
+        exposure_caps: ExposureCaps { max_bet_amount: Some(Uint128::new(5)), ..ExposureCaps::default() },
+    })
+    .unwrap_err();
+    assert!(matches!(err, ContractError::InvalidExposureCaps {}));
+
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetExposureCaps {
+        exposure_caps: ExposureCaps {
+            max_bet_amount: Some(Uint128::new(100)),
+            max_user_round_amount: Some(Uint128::new(150)),
+            max_round_amount: Some(Uint128::new(400)),
+            max_imbalance_ratio: Some(Decimal::percent(200)),
+        },
+    })
+    .unwrap();
+    start_rounds(&mut deps);
+
+    let err = bet(&mut deps, ALICE, 1, Position::Bull, 101).unwrap_err();
+    assert!(matches!(err, ContractError::BetTooLarge {}));
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    let err = bet(&mut deps, ALICE, 1, Position::Bull, 60).unwrap_err();
+    assert!(matches!(err, ContractError::UserRoundCapExceeded {}));
+
+    // Bull 100 against Bear 40 is more than twice the lighter side
+    bet(&mut deps, BOB, 1, Position::Bear, 40).unwrap();
+    let err = bet(&mut deps, CAROL, 1, Position::Bull, 10).unwrap_err();
+    assert!(matches!(err, ContractError::ImbalanceExceeded {}));
+    bet(&mut deps, BOB, 1, Position::Bear, 100).unwrap();
+    bet(&mut deps, CAROL, 1, Position::Bull, 100).unwrap();
+
+    let err = bet(&mut deps, DAVE, 1, Position::Bear, 70).unwrap_err();
+    assert!(matches!(err, ContractError::RoundCapExceeded {}));
+    bet(&mut deps, DAVE, 1, Position::Bear, 60).unwrap();
+    assert_eq!(load_round(&deps, 1).total_amount, Uint128::new(400));
+}
+
+#[test]
+fn flat_wins_a_move_within_the_band() {
+    let mut deps = setup();
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetFlatBand { flat_band_bps: Some(0) }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidFlatBand {}));
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetFlatBand { flat_band_bps: Some(100) }).unwrap();
+
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Flat, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bull, 100).unwrap();
+    bet(&mut deps, CAROL, 1, Position::Bear, 100).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+    run_round(&mut deps, close_time(1), 6_060_000);
+
+    assert_eq!(winning_position(&load_round(&deps, 1)), Some(Position::Flat));
+    claim(&mut deps, close_time(1), ALICE, 1).unwrap();
+    assert_eq!(balance(&deps, ALICE), 900 + 291);
+    let err = claim(&mut deps, close_time(1), BOB, 1).unwrap_err();
+    assert!(matches!(err, ContractError::NotWinner { epoch: 1 }));
+
+    let mut round = load_round(&deps, 1);
+    round.close_price = 6_060_001;
+    assert_eq!(winning_position(&round), Some(Position::Bull));
+}
+
+#[test]
+fn range_rounds_pay_the_bucket_the_move_lands_in() {
+    let mut deps = setup();
+    let set_kind = |boundaries_bps: Vec<i64>| ExecuteMsg::SetMarketKind { market_kind: MarketKind::Range { boundaries_bps } };
+    let err = exec(&mut deps, GENESIS, ADMIN, set_kind(vec![100, -100])).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMarketKind {}));
+    let err = exec(&mut deps, GENESIS, ADMIN, set_kind(vec![])).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMarketKind {}));
+    exec(&mut deps, GENESIS, ADMIN, set_kind(vec![-100, 100])).unwrap();
+
+    start_rounds(&mut deps);
+    assert_eq!(load_round(&deps, 1).bucket_amounts.len(), 3);
+    let err = bet(&mut deps, ALICE, 1, Position::Bucket(3), 100).unwrap_err();
+    assert!(matches!(err, ContractError::PositionNotOffered { epoch: 1 }));
+    let err = bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap_err();
+    assert!(matches!(err, ContractError::PositionNotOffered { epoch: 1 }));
+
+    bet(&mut deps, ALICE, 1, Position::Bucket(2), 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bucket(1), 100).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+    // Exactly +1% lands on the boundary and falls into the bucket above it
+    run_round(&mut deps, close_time(1), 6_060_000);
+
+    assert_eq!(winning_position(&load_round(&deps, 1)), Some(Position::Bucket(2)));
+    claim(&mut deps, close_time(1), ALICE, 1).unwrap();
+    assert_eq!(balance(&deps, ALICE), 900 + 194);
+}
+
+#[test]
+fn strike_offset_refunds_a_close_within_the_offset() {
+    let mut deps = setup();
+    let set_offset = |offset: StrikeOffset| ExecuteMsg::SetMarketKind { market_kind: MarketKind::StrikeOffset { offset } };
+    let err = exec(&mut deps, GENESIS, ADMIN, set_offset(StrikeOffset::Bps(0))).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMarketKind {}));
+    exec(&mut deps, GENESIS, ADMIN, set_offset(StrikeOffset::Absolute(Uint128::new(50_000)))).unwrap();
+
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bear, 100).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+    run_round(&mut deps, close_time(1), 6_040_000);
+
+    let round = load_round(&deps, 1);
+    assert!(is_refund_round(&round));
+    assert!(!PENDING_ROUND_FEES.has(&deps.storage, 1));
+    claim(&mut deps, close_time(1), ALICE, 1).unwrap();
+    claim(&mut deps, close_time(1), BOB, 1).unwrap();
+    assert_eq!((balance(&deps, ALICE), balance(&deps, BOB)), (1000, 1000));
+
+    let mut round = round;
+    round.close_price = 6_050_001;
+    assert_eq!(winning_position(&round), Some(Position::Bull));
+    round.close_price = 5_949_999;
+    assert_eq!(winning_position(&round), Some(Position::Bear));
+}
+
+#[test]
+fn fixed_strike_market_resolves_on_a_reading_within_the_window() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::CreateMarket {
+        description: "BTC above 60000".to_string(),
+        strike_price: Uint128::new(6_000_000),
+        betting_deadline: GENESIS + 100,
+        resolution_time: GENESIS + 200,
+    })
+    .unwrap();
+    let bet_market = |position: Position| ExecuteMsg::BetMarket { market_id: 1, position, amount: Uint128::new(100) };
+    exec(&mut deps, GENESIS + 50, ALICE, bet_market(Position::Yes)).unwrap();
+    exec(&mut deps, GENESIS + 50, BOB, bet_market(Position::No)).unwrap();
+    let err = exec(&mut deps, GENESIS + 100, CAROL, bet_market(Position::Yes)).unwrap_err();
+    assert!(matches!(err, ContractError::MarketNotBettable { market_id: 1 }));
+
+    set_price(&mut deps, GENESIS + 190, 6_100_000);
+    let err = exec(&mut deps, GENESIS + 190, OPERATOR, ExecuteMsg::ResolveMarket { market_id: 1 }).unwrap_err();
+    assert!(matches!(err, ContractError::MarketNotResolvable { market_id: 1 }));
+
+    set_price(&mut deps, GENESIS + 230, 6_100_000);
+    exec(&mut deps, GENESIS + 230, OPERATOR, ExecuteMsg::ResolveMarket { market_id: 1 }).unwrap();
+    let market = MARKETS.load(&deps.storage, 1).unwrap();
+    assert_eq!((market.outcome, market.settlement_price), (Some(Position::Yes), Some(6_100_000)));
+    // Claims open at once, so the fee goes straight to the treasury
+    assert!(!PENDING_MARKET_FEES.has(&deps.storage, 1));
+    assert_eq!(treasury(&deps), 6);
+
+    exec(&mut deps, GENESIS + 230, ALICE, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap();
+    assert_eq!(balance(&deps, ALICE), 900 + 194);
+    let res = exec(&mut deps, GENESIS + 230, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "6");
+}
+
+#[test]
+fn fixed_strike_market_is_cancelled_once_the_window_passes_without_a_reading() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::CreateMarket {
+        description: "BTC above 60000".to_string(),
+        strike_price: Uint128::new(6_000_000),
+        betting_deadline: GENESIS + 100,
+        resolution_time: GENESIS + 200,
+    })
+    .unwrap();
+    exec(&mut deps, GENESIS + 50, ALICE, ExecuteMsg::BetMarket { market_id: 1, position: Position::Yes, amount: Uint128::new(100) }).unwrap();
+
+    // Within the window a missing reading is only an error
+    set_pyth(&mut deps, &[]);
+    let err = exec(&mut deps, GENESIS + 250, OPERATOR, ExecuteMsg::ResolveMarket { market_id: 1 }).unwrap_err();
+    assert!(matches!(err, ContractError::OracleError(_)));
+
+    // A fresh reading published too long after resolution_time can't qualify
+    set_price(&mut deps, GENESIS + 261, 6_100_000);
+    let res = exec(&mut deps, GENESIS + 261, OPERATOR, ExecuteMsg::ResolveMarket { market_id: 1 }).unwrap();
+    assert!(has_event(&res, "cancel_market"));
+    assert!(MARKETS.load(&deps.storage, 1).unwrap().cancelled);
+
+    exec(&mut deps, GENESIS + 261, ALICE, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap();
+    assert_eq!(balance(&deps, ALICE), 1000);
+}
+
+#[test]
+fn relative_rounds_compare_the_returns_of_both_feeds() {
+    let mut deps = setup();
+    let relative = |feed_id_b: &str| ExecuteMsg::SetMarketKind {
+        market_kind: MarketKind::Relative { feed_id_a: BTC_FEED.to_string(), feed_id_b: feed_id_b.to_string() },
+    };
+    let err = exec(&mut deps, GENESIS, ADMIN, relative(BTC_FEED)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMarketKind {}));
+    exec(&mut deps, GENESIS, ADMIN, relative(ETH_FEED)).unwrap();
+
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bear, 100).unwrap();
+    set_pyth(&mut deps, &[(BTC_FEED, 6_000_000, 0, lock_time(1)), (ETH_FEED, 300_000, 0, lock_time(1))]);
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+
+    // BTC gains 10% and ETH 5%
+    set_pyth(&mut deps, &[(BTC_FEED, 6_600_000, 0, close_time(1)), (ETH_FEED, 315_000, 0, close_time(1))]);
+    exec(&mut deps, close_time(1), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+
+    let round = load_round(&deps, 1);
+    assert_eq!((round.lock_price_b, round.close_price_b), (300_000, 315_000));
+    assert_eq!(winning_position(&round), Some(Position::Bull));
+    claim(&mut deps, close_time(1), ALICE, 1).unwrap();
+    assert_eq!(balance(&deps, ALICE), 900 + 194);
+}
+
+#[test]
+fn volatility_rounds_pay_volatile_beyond_the_threshold() {
+    let mut deps = setup();
+    let volatility = |threshold_bps: u64| ExecuteMsg::SetMarketKind { market_kind: MarketKind::Volatility { threshold_bps } };
+    let err = exec(&mut deps, GENESIS, ADMIN, volatility(0)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMarketKind {}));
+    exec(&mut deps, GENESIS, ADMIN, volatility(100)).unwrap();
+
+    start_rounds(&mut deps);
+    let err = bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap_err();
+    assert!(matches!(err, ContractError::PositionNotOffered { epoch: 1 }));
+    bet(&mut deps, ALICE, 1, Position::Volatile, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Calm, 100).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+    run_round(&mut deps, close_time(1), 5_900_000);
+
+    let mut round = load_round(&deps, 1);
+    assert_eq!(winning_position(&round), Some(Position::Volatile));
+    claim(&mut deps, close_time(1), ALICE, 1).unwrap();
+    assert_eq!(balance(&deps, ALICE), 900 + 194);
+
+    // A move of exactly the threshold is still calm
+    round.close_price = 5_940_000;
+    assert_eq!(winning_position(&round), Some(Position::Calm));
+}
+
+fn create_event_market(deps: &mut TestDeps, quorum: u32) -> Result<Response, ContractError> {
+    exec(deps, GENESIS, ADMIN, ExecuteMsg::CreateEventMarket {
+        description: "Event".to_string(),
+        resolvers: vec!["resolver1".to_string(), "resolver2".to_string(), "resolver3".to_string()],
+        quorum,
+        betting_deadline: GENESIS + 100,
+        resolution_time: GENESIS + 200,
+        vote_window_seconds: 100,
+        dispute_window_seconds: 50,
+        dispute_bond: Uint128::new(50),
+    })
+}
+
+// Committee market with Alice on Yes and Bob on No for 100 each
+fn event_market_with_bets(deps: &mut TestDeps, quorum: u32) {
+    create_event_market(deps, quorum).unwrap();
+    exec(deps, GENESIS + 50, ALICE, ExecuteMsg::BetMarket { market_id: 1, position: Position::Yes, amount: Uint128::new(100) }).unwrap();
+    exec(deps, GENESIS + 50, BOB, ExecuteMsg::BetMarket { market_id: 1, position: Position::No, amount: Uint128::new(100) }).unwrap();
+}
+
+fn vote(deps: &mut TestDeps, time: u64, resolver: &str, outcome: Position) -> Result<Response, ContractError> {
+    exec(deps, time, resolver, ExecuteMsg::VoteMarket { market_id: 1, outcome })
+}
+
+#[test]
+fn committee_market_resolves_at_quorum_and_pays_after_the_dispute_window() {
+    let mut deps = setup();
+    let err = create_event_market(&mut deps, 0).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMarket {}));
+    event_market_with_bets(&mut deps, 2);
+
+    let err = vote(&mut deps, GENESIS + 210, CAROL, Position::Yes).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    let err = vote(&mut deps, GENESIS + 190, "resolver1", Position::Yes).unwrap_err();
+    assert!(matches!(err, ContractError::MarketNotResolvable { market_id: 1 }));
+
+    vote(&mut deps, GENESIS + 210, "resolver1", Position::Yes).unwrap();
+    assert!(MARKETS.load(&deps.storage, 1).unwrap().outcome.is_none());
+    let res = vote(&mut deps, GENESIS + 220, "resolver2", Position::Yes).unwrap();
+    assert_eq!(event_attr(&res, "resolve_market", "claims_open_at").unwrap(), (GENESIS + 270).to_string());
+    let err = vote(&mut deps, GENESIS + 230, "resolver3", Position::No).unwrap_err();
+    assert!(matches!(err, ContractError::MarketAlreadyResolved { market_id: 1 }));
+
+    // The fee stays pending through the dispute window
+    let err = exec(&mut deps, GENESIS + 230, ALICE, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap_err();
+    assert!(matches!(err, ContractError::MarketInDispute { market_id: 1 }));
+    let err = exec(&mut deps, GENESIS + 230, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap_err();
+    assert!(matches!(err, ContractError::NoTreasury {}));
+
+    exec(&mut deps, GENESIS + 270, ALICE, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap();
+    assert_eq!(balance(&deps, ALICE), 900 + 194);
+    let res = exec(&mut deps, GENESIS + 270, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "6");
+}
+
+#[test]
+fn a_bettor_dispute_holds_claims_until_the_admin_rules() {
+    let mut deps = setup();
+    event_market_with_bets(&mut deps, 1);
+    vote(&mut deps, GENESIS + 210, "resolver1", Position::Yes).unwrap();
+
+    let dispute = ExecuteMsg::DisputeMarket { market_id: 1 };
+    let err = exec(&mut deps, GENESIS + 220, CAROL, dispute.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::NoMarketBet { market_id: 1 }));
+    exec(&mut deps, GENESIS + 220, BOB, dispute.clone()).unwrap();
+    let err = exec(&mut deps, GENESIS + 220, ALICE, dispute).unwrap_err();
+    assert!(matches!(err, ContractError::MarketAlreadyDisputed { market_id: 1 }));
+
+    let err = exec(&mut deps, GENESIS + 300, ALICE, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap_err();
+    assert!(matches!(err, ContractError::MarketInDispute { market_id: 1 }));
+    let err = exec(&mut deps, GENESIS + 300, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap_err();
+    assert!(matches!(err, ContractError::NoTreasury {}));
+
+    // Overturning the outcome returns the bond taken from Bob's balance
+    assert_eq!(balance(&deps, BOB), 900 - 50);
+    let res = exec(&mut deps, GENESIS + 300, ADMIN, ExecuteMsg::ResolveMarketDispute { market_id: 1, outcome: Position::No }).unwrap();
+    assert_eq!(event_attr(&res, "resolve_market_dispute", "overturned").unwrap(), "true");
+    exec(&mut deps, GENESIS + 300, BOB, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap();
+    assert_eq!(balance(&deps, BOB), 900 + 194);
+    let err = exec(&mut deps, GENESIS + 300, ALICE, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap_err();
+    assert!(matches!(err, ContractError::NotMarketWinner { market_id: 1 }));
+    let res = exec(&mut deps, GENESIS + 300, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "6");
+}
+
+#[test]
+fn a_dispute_the_admin_rejects_forfeits_the_bond_to_the_treasury() {
+    let mut deps = setup();
+    event_market_with_bets(&mut deps, 1);
+    vote(&mut deps, GENESIS + 210, "resolver1", Position::Yes).unwrap();
+
+    let dispute = ExecuteMsg::DisputeMarket { market_id: 1 };
+    let err = exec_with_funds(&mut deps, GENESIS + 220, BOB, &[coin(10, USDC)], dispute.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidBetFunds));
+    let res = exec_with_funds(&mut deps, GENESIS + 220, BOB, &[coin(50, USDC)], dispute).unwrap();
+    assert_eq!(attr(&res, "funding"), "funds");
+    assert_eq!(balance(&deps, BOB), 900);
+
+    let res = exec(&mut deps, GENESIS + 300, ADMIN, ExecuteMsg::ResolveMarketDispute { market_id: 1, outcome: Position::Yes }).unwrap();
+    assert_eq!(event_attr(&res, "resolve_market_dispute", "overturned").unwrap(), "false");
+    assert_eq!(balance(&deps, BOB), 900);
+    exec(&mut deps, GENESIS + 300, ALICE, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap();
+    assert_eq!(balance(&deps, ALICE), 900 + 194);
+    let res = exec(&mut deps, GENESIS + 300, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "56");
+}
+
+#[test]
+fn an_event_market_with_a_dispute_window_needs_a_dispute_bond() {
+    let mut deps = setup();
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::CreateEventMarket {
+        description: "Event".to_string(),
+        resolvers: vec!["resolver1".to_string()],
+        quorum: 1,
+        betting_deadline: GENESIS + 100,
+        resolution_time: GENESIS + 200,
+        vote_window_seconds: 100,
+        dispute_window_seconds: 50,
+        dispute_bond: Uint128::zero(),
+    })
+    .unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMarket {}));
+}
+
+#[test]
+fn cancelling_a_resolved_market_drops_its_pending_fee() {
+    let mut deps = setup();
+    event_market_with_bets(&mut deps, 1);
+    vote(&mut deps, GENESIS + 210, "resolver1", Position::Yes).unwrap();
+
+    exec(&mut deps, GENESIS + 220, ADMIN, ExecuteMsg::CancelMarket { market_id: 1 }).unwrap();
+    assert!(!PENDING_MARKET_FEES.has(&deps.storage, 1));
+    for user in [ALICE, BOB] {
+        exec(&mut deps, GENESIS + 220, user, ExecuteMsg::ClaimMarkets { market_ids: vec![1] }).unwrap();
+        assert_eq!(balance(&deps, user), 1000);
+    }
+    let err = exec(&mut deps, GENESIS + 300, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap_err();
+    assert!(matches!(err, ContractError::NoTreasury {}));
+}
+
+#[test]
+fn anyone_can_cancel_a_committee_market_that_missed_quorum() {
+    let mut deps = setup();
+    event_market_with_bets(&mut deps, 2);
+    vote(&mut deps, GENESIS + 210, "resolver1", Position::Yes).unwrap();
+
+    let err = exec(&mut deps, GENESIS + 299, CAROL, ExecuteMsg::CancelMarket { market_id: 1 }).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    let res = exec(&mut deps, GENESIS + 300, CAROL, ExecuteMsg::CancelMarket { market_id: 1 }).unwrap();
+    assert_eq!(event_attr(&res, "cancel_market", "quorum_missed").unwrap(), "true");
+}
+
+#[test]
+fn settlement_fees_stay_pending_through_the_challenge_window() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    settle_bull_round(&mut deps);
+
+    assert_eq!(load_round(&deps, 1).claims_open_at, close_time(1) + 100);
+    assert_eq!(PENDING_ROUND_FEES.load(&deps.storage, 1).unwrap(), Uint128::new(6));
+    let err = claim(&mut deps, close_time(1), ALICE, 1).unwrap_err();
+    assert!(matches!(err, ContractError::ClaimsNotOpen { epoch: 1 }));
+    let err = exec(&mut deps, close_time(1), ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap_err();
+    assert!(matches!(err, ContractError::NoTreasury {}));
+
+    let res = exec(&mut deps, close_time(1) + 100, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "6");
+    assert!(!PENDING_ROUND_FEES.has(&deps.storage, 1));
+}
+
+#[test]
+fn the_first_claim_moves_the_pending_fee_into_the_treasury() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    settle_bull_round(&mut deps);
+
+    claim(&mut deps, close_time(1) + 100, ALICE, 1).unwrap();
+    assert!(!PENDING_ROUND_FEES.has(&deps.storage, 1));
+    assert_eq!(treasury(&deps), 6);
+}
+
+#[test]
+fn claim_treasury_releases_a_bounded_page_of_unclaimed_fees() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    settle_bull_round(&mut deps);
+
+    // Fees of settled rounds nobody has claimed from yet
+    let round = load_round(&deps, 1);
+    for epoch in 1..=31 {
+        ROUNDS.save(&mut deps.storage, epoch, &Round { epoch, ..round.clone() }).unwrap();
+        PENDING_ROUND_FEES.save(&mut deps.storage, epoch, &Uint128::new(1)).unwrap();
+    }
+
+    let res = exec(&mut deps, close_time(1) + 100, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "30");
+    assert!(PENDING_ROUND_FEES.has(&deps.storage, 31));
+    let res = exec(&mut deps, close_time(1) + 100, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "1");
+}
+
+#[test]
+fn a_challenge_the_pyth_reading_proves_is_upheld_on_verification() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bear, 100).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+    // Settled on a reading published 20 seconds before the close
+    set_price(&mut deps, close_time(1) - 20, 6_100_000);
+    exec(&mut deps, close_time(1), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+
+    let res = challenge(&mut deps, close_time(1) + 10, BOB, 5_900_000).unwrap();
+    assert_eq!(attr(&res, "funding"), "balance");
+    assert_eq!(balance(&deps, BOB), 850);
+    let config = CONFIG.load(&deps.storage).unwrap();
+    let update = update_price_feeds_msg(&config.oracle_address, vec![Binary::from(b"vaa".to_vec())], coin(1, "uatom")).unwrap();
+    assert_eq!(res.messages[0].msg, update);
+    assert_eq!(res.messages.len(), 2);
+
+    let err = exec(&mut deps, close_time(1) + 10, BOB, ExecuteMsg::VerifyChallenge { epoch: 1 }).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    // The applied update holds a reading right at the round's close that backs Bob's price
+    set_pyth(&mut deps, &[(BTC_FEED, 5_900_000, 1_000, close_time(1))]);
+    let res = verify_challenge(&mut deps, close_time(1) + 10);
+    assert_eq!(event_attr(&res, "verify_challenge", "verified").unwrap(), "true");
+    assert_eq!(event_attr(&res, "resolve_challenge", "payout").unwrap(), "56");
+
+    let round = load_round(&deps, 1);
+    assert_eq!(round.close_price, 5_900_000);
+    assert_eq!(winning_position(&round), Some(Position::Bear));
+    assert!(!PENDING_ROUND_FEES.has(&deps.storage, 1));
+
+    // The fee of the corrected settlement went to Bob, so none reaches the treasury
+    claim(&mut deps, close_time(1) + 100, BOB, 1).unwrap();
+    assert_eq!(balance(&deps, BOB), 850 + 56 + 194);
+    let err = exec(&mut deps, close_time(1) + 100, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap_err();
+    assert!(matches!(err, ContractError::NoTreasury {}));
+}
+
+#[test]
+fn a_reading_no_nearer_the_close_than_the_settled_one_proves_nothing() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    settle_bull_round(&mut deps);
+    challenge(&mut deps, close_time(1) + 10, BOB, 5_900_000).unwrap();
+
+    // A later reading inside the price window does not outrank the one settled at the close
+    set_pyth(&mut deps, &[(BTC_FEED, 5_900_000, 1_000, close_time(1) + 20)]);
+    let res = verify_challenge(&mut deps, close_time(1) + 30);
+    assert_eq!(event_attr(&res, "verify_challenge", "verified").unwrap(), "false");
+    assert!(CHALLENGES.load(&deps.storage, 1).unwrap().upheld.is_none());
+    assert_eq!(load_round(&deps, 1).close_price, 6_100_000);
+}
+
+#[test]
+fn a_proven_correction_that_keeps_the_winner_waits_for_the_admin() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bear, 100).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+    set_price(&mut deps, close_time(1) - 20, 6_100_000);
+    exec(&mut deps, close_time(1), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+    challenge(&mut deps, close_time(1) + 10, BOB, 6_050_000).unwrap();
+
+    // Bull still wins at the proven price, so the round is not resettled automatically
+    set_pyth(&mut deps, &[(BTC_FEED, 6_050_000, 1_000, close_time(1))]);
+    let res = verify_challenge(&mut deps, close_time(1) + 10);
+    assert_eq!(event_attr(&res, "verify_challenge", "verified").unwrap(), "false");
+    assert!(CHALLENGES.load(&deps.storage, 1).unwrap().upheld.is_none());
+}
+
+#[test]
+fn an_unproven_challenge_waits_for_the_admin() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    settle_bull_round(&mut deps);
+
+    challenge(&mut deps, close_time(1) + 10, BOB, 5_900_000).unwrap();
+    let err = challenge(&mut deps, close_time(1) + 10, CAROL, 5_900_000).unwrap_err();
+    assert!(matches!(err, ContractError::AlreadyChallenged { epoch: 1 }));
+
+    // Pyth still holds the settled reading
+    let res = verify_challenge(&mut deps, close_time(1) + 10);
+    assert_eq!(event_attr(&res, "verify_challenge", "verified").unwrap(), "false");
+    assert!(CHALLENGES.load(&deps.storage, 1).unwrap().upheld.is_none());
+
+    // Claims stay closed past the window until the challenge is decided
+    let err = claim(&mut deps, close_time(1) + 100, ALICE, 1).unwrap_err();
+    assert!(matches!(err, ContractError::ClaimsNotOpen { epoch: 1 }));
+
+    // The forfeited bond and the fee of the now open claims both reach the treasury
+    exec(&mut deps, close_time(1) + 100, ADMIN, ExecuteMsg::ResolveChallenge { epoch: 1, upheld: false }).unwrap();
+    assert_eq!(treasury(&deps), 56);
+    assert!(!PENDING_ROUND_FEES.has(&deps.storage, 1));
+    claim(&mut deps, close_time(1) + 100, ALICE, 1).unwrap();
+    let res = exec(&mut deps, close_time(1) + 100, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "56");
+}
+
+#[test]
+fn a_challenge_within_the_settled_confidence_is_rejected() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bear, 100).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+    set_pyth(&mut deps, &[(BTC_FEED, 6_100_000, 50_000, close_time(1))]);
+    exec(&mut deps, close_time(1), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+
+    let err = challenge(&mut deps, close_time(1) + 10, BOB, 6_060_000).unwrap_err();
+    assert!(matches!(err, ContractError::ChallengeWithinConfidence { epoch: 1 }));
+    let err = challenge(&mut deps, close_time(1) + 10, BOB, 6_100_000).unwrap_err();
+    assert!(matches!(err, ContractError::ChallengePriceUnchanged { epoch: 1 }));
+    let err = challenge(&mut deps, close_time(1) + 100, BOB, 5_900_000).unwrap_err();
+    assert!(matches!(err, ContractError::ChallengeWindowClosed { epoch: 1 }));
+}
+
+fn feeder(address: &str) -> PriceSource {
+    PriceSource::Feeder { address: Addr::unchecked(address) }
+}
+
+fn submit_price(deps: &mut TestDeps, time: u64, feeder: &str, price: u128) -> Result<Response, ContractError> {
+    exec(deps, time, feeder, ExecuteMsg::SubmitPrice { price_feed_id: BTC_FEED.to_string(), price: Uint128::new(price), expo: -2 })
+}
+
+#[test]
+fn rounds_lock_on_the_median_of_the_sources() {
+    let mut deps = setup();
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources {
+        price_sources: vec![feeder("feeder1"), feeder("feeder1")],
+        max_deviation_bps: 200,
+    })
+    .unwrap_err();
+    assert!(matches!(err, ContractError::InvalidPriceSources {}));
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources {
+        price_sources: vec![feeder("feeder1"), feeder("feeder2"), feeder("feeder3")],
+        max_deviation_bps: 0,
+    })
+    .unwrap_err();
+    assert!(matches!(err, ContractError::InvalidPriceSources {}));
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources {
+        price_sources: vec![feeder("feeder1"), feeder("feeder2"), feeder("feeder3")],
+        max_deviation_bps: 200,
+    })
+    .unwrap();
+
+    let err = submit_price(&mut deps, GENESIS, CAROL, 6_000_000).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    start_rounds(&mut deps);
+    submit_price(&mut deps, lock_time(1) - 10, "feeder1", 6_000_000).unwrap();
+    submit_price(&mut deps, lock_time(1) - 10, "feeder2", 6_090_000).unwrap();
+    submit_price(&mut deps, lock_time(1) - 10, "feeder3", 6_030_000).unwrap();
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+
+    let round = load_round(&deps, 1);
+    assert_eq!(round.lock_price, 6_030_000);
+    assert_eq!(round.lock_price_origin, Some(PriceOrigin::Primary));
+}
+
+#[test]
+fn sources_beyond_the_deviation_cancel_the_round_without_falling_back() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources {
+        price_sources: vec![feeder("feeder1"), feeder("feeder2"), feeder("feeder3")],
+        max_deviation_bps: 200,
+    })
+    .unwrap();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetFallbackPriceSource { fallback_price_source: Some(feeder("feeder4")) }).unwrap();
+
+    start_rounds(&mut deps);
+    submit_price(&mut deps, lock_time(1) - 10, "feeder1", 6_000_000).unwrap();
+    submit_price(&mut deps, lock_time(1) - 10, "feeder2", 6_030_000).unwrap();
+    submit_price(&mut deps, lock_time(1) - 10, "feeder3", 6_200_000).unwrap();
+    submit_price(&mut deps, lock_time(1) - 10, "feeder4", 6_030_000).unwrap();
+    let res = exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+
+    assert!(has_event(&res, "cancel_round"));
+    let round = load_round(&deps, 1);
+    assert!(round.cancelled);
+    assert!(round.lock_price_info.is_none());
+}
+
+#[test]
+fn a_stale_oracle_falls_back_when_no_sources_are_set() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetFallbackPriceSource { fallback_price_source: Some(feeder("feeder1")) }).unwrap();
+
+    start_rounds(&mut deps);
+    set_price(&mut deps, GENESIS, 5_000_000);
+    submit_price(&mut deps, lock_time(1) - 10, "feeder1", 6_000_000).unwrap();
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+
+    let round = load_round(&deps, 1);
+    assert_eq!(round.lock_price, 6_000_000);
+    assert_eq!(round.lock_price_origin, Some(PriceOrigin::Fallback));
+}
+
+#[test]
+fn prices_too_large_to_average_cancel_the_round() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources {
+        price_sources: vec![feeder("feeder1"), feeder("feeder2")],
+        max_deviation_bps: 200,
+    })
+    .unwrap();
+
+    start_rounds(&mut deps);
+    submit_price(&mut deps, lock_time(1) - 10, "feeder1", i128::MAX as u128).unwrap();
+    submit_price(&mut deps, lock_time(1) - 10, "feeder2", i128::MAX as u128).unwrap();
+    let res = exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+
+    assert!(has_event(&res, "cancel_round"));
+    assert!(load_round(&deps, 1).cancelled);
+}
+
+#[test]
+fn a_stale_primary_source_falls_back() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources {
+        price_sources: vec![feeder("feeder1")],
+        max_deviation_bps: 100,
+    })
+    .unwrap();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetFallbackPriceSource {
+        fallback_price_source: Some(PriceSource::Pyth { oracle_address: Addr::unchecked(PYTH) }),
+    })
+    .unwrap();
+
+    start_rounds(&mut deps);
+    submit_price(&mut deps, GENESIS, "feeder1", 5_000_000).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+
+    let round = load_round(&deps, 1);
+    assert_eq!(round.lock_price, 6_000_000);
+    assert_eq!(round.lock_price_origin, Some(PriceOrigin::Fallback));
+}
+
+#[test]
+fn osmosis_twap_sources_are_validated() {
+    let mut deps = setup();
+    let twap = |window_seconds: u64, expo: i32| PriceSource::OsmosisTwap {
+        pools: vec![TwapPool {
+            price_feed_id: BTC_FEED.to_string(),
+            pool_id: 1,
+            base_asset: "ubtc".to_string(),
+            quote_asset: "uusdc".to_string(),
+        }],
+        window_seconds,
+        expo,
+    };
+    for source in [twap(0, -2), twap(600, 2)] {
+        let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources { price_sources: vec![source], max_deviation_bps: 100 }).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPriceSources {}));
+    }
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources { price_sources: vec![twap(600, -2)], max_deviation_bps: 100 }).unwrap();
+
+    let err = source_price(deps.as_ref(), &at(GENESIS), &twap(600, -2), ETH_FEED, None).unwrap_err();
+    assert!(matches!(err, ContractError::OracleError(msg) if msg.contains("No Osmosis pool")));
+}
+
+#[test]
+fn rounds_store_the_reading_behind_each_price() {
+    let mut deps = setup();
+    start_rounds(&mut deps);
+    set_pyth(&mut deps, &[(BTC_FEED, 6_000_000, 1_234, lock_time(1) - 5)]);
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+
+    let round = query_round(deps.as_ref(), 1).unwrap();
+    assert_eq!(round.lock_price_info, Some(PriceInfo {
+        price: 6_000_000,
+        expo: -2,
+        conf: 1_234,
+        publish_time: lock_time(1) - 5,
+    }));
+    assert_eq!(round.lock_price_origin, Some(PriceOrigin::Oracle));
+}
+
+#[test]
+fn a_reading_outside_the_price_window_cancels_the_round() {
+    let mut deps = setup();
+    start_rounds(&mut deps);
+
+    let err = exec(&mut deps, lock_time(1) - 1, OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap_err();
+    assert!(matches!(err, ContractError::RoundNotLockable { epoch: 1 }));
+
+    // Locking late with a fresh reading is still 90 seconds off the lock timestamp
+    set_price(&mut deps, lock_time(1) + 90, 6_000_000);
+    let res = exec(&mut deps, lock_time(1) + 90, OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+    assert!(has_event(&res, "cancel_round"));
+    assert!(load_round(&deps, 1).cancelled);
+
+    // A cancelled round still hands over to the next one
+    exec(&mut deps, lock_time(1) + 90, OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+    assert_eq!(CURRENT_EPOCH.load(&deps.storage).unwrap(), 2);
+}
+
+#[test]
+fn a_wider_price_window_accepts_a_late_reading() {
+    let mut deps = setup();
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceWindow { price_window_seconds: 0 }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidPriceWindow {}));
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceWindow { price_window_seconds: 120 }).unwrap();
+
+    start_rounds(&mut deps);
+    set_price(&mut deps, lock_time(1) + 90, 6_000_000);
+    exec(&mut deps, lock_time(1) + 90, OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+
+    let round = load_round(&deps, 1);
+    assert!(!round.cancelled);
+    assert_eq!(round.lock_price, 6_000_000);
+}
+
+#[test]
+fn a_lock_to_close_jump_halts_settlement_but_not_betting() {
+    let mut deps = setup();
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetCircuitBreaker { max_price_move_bps: Some(0) }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidCircuitBreaker {}));
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetCircuitBreaker { max_price_move_bps: Some(1000) }).unwrap();
+
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bear, 100).unwrap();
+    lock_genesis(&mut deps, 6_000_000);
+    let res = run_round(&mut deps, close_time(1), 7_000_000);
+
+    assert_eq!(event_attr(&res, "circuit_breaker", "check").unwrap(), "lock_to_close");
+    let round = load_round(&deps, 1);
+    assert!(round.cancelled && round.flagged);
+    assert!(!PENDING_ROUND_FEES.has(&deps.storage, 1));
+    assert!(SETTLEMENT_HALTED.load(&deps.storage).unwrap());
+    assert!(!PAUSED.load(&deps.storage).unwrap());
+
+    // Bets and refunds go on while settlement is halted
+    claim(&mut deps, close_time(1), ALICE, 1).unwrap();
+    assert_eq!(balance(&deps, ALICE), 1000);
+    bet(&mut deps, CAROL, 2, Position::Bull, 100).unwrap();
+
+    set_price(&mut deps, lock_time(2), 6_000_000);
+    let err = exec(&mut deps, lock_time(2), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap_err();
+    assert!(matches!(err, ContractError::SettlementHalted {}));
+
+    let err = exec(&mut deps, lock_time(2), CAROL, ExecuteMsg::ResumeSettlement {}).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    exec(&mut deps, lock_time(2), ADMIN, ExecuteMsg::ResumeSettlement {}).unwrap();
+    let err = exec(&mut deps, lock_time(2), ADMIN, ExecuteMsg::ResumeSettlement {}).unwrap_err();
+    assert!(matches!(err, ContractError::SettlementNotHalted {}));
+
+    run_round(&mut deps, lock_time(2), 6_000_000);
+    assert_eq!(load_round(&deps, 2).lock_price, 6_000_000);
+    assert_eq!(CURRENT_EPOCH.load(&deps.storage).unwrap(), 3);
+}
+
+#[test]
+fn a_round_to_round_jump_stops_progression_at_once() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetCircuitBreaker { max_price_move_bps: Some(1000) }).unwrap();
+    start_rounds(&mut deps);
+    lock_genesis(&mut deps, 6_000_000);
+    run_round(&mut deps, close_time(1), 6_000_000);
+
+    let res = run_round(&mut deps, lock_time(2), 7_000_000);
+    assert_eq!(event_attr(&res, "circuit_breaker", "check").unwrap(), "round_to_round");
+    assert!(load_round(&deps, 2).flagged);
+    assert_eq!(CURRENT_EPOCH.load(&deps.storage).unwrap(), 2);
+    assert!(!ROUNDS.has(&deps.storage, 3));
+}
+
+#[test]
+fn the_circuit_breaker_checks_the_second_feed_of_relative_rounds() {
+    let mut deps = setup();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetCircuitBreaker { max_price_move_bps: Some(1000) }).unwrap();
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetMarketKind {
+        market_kind: MarketKind::Relative { feed_id_a: BTC_FEED.to_string(), feed_id_b: ETH_FEED.to_string() },
+    })
+    .unwrap();
+
+    start_rounds(&mut deps);
+    set_pyth(&mut deps, &[(BTC_FEED, 6_000_000, 0, lock_time(1)), (ETH_FEED, 300_000, 0, lock_time(1))]);
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+
+    // BTC is flat while ETH gains a third
+    set_pyth(&mut deps, &[(BTC_FEED, 6_000_000, 0, close_time(1)), (ETH_FEED, 400_000, 0, close_time(1))]);
+    let res = exec(&mut deps, close_time(1), OPERATOR, ExecuteMsg::ExecuteRound { attestations: None }).unwrap();
+
+    assert_eq!(event_attr(&res, "circuit_breaker", "from_price").unwrap(), "300000");
+    assert!(load_round(&deps, 1).flagged);
+    assert!(SETTLEMENT_HALTED.load(&deps.storage).unwrap());
+}
+
+fn attest(key: &SigningKey, epoch: u64, phase: PricePhase, price: u128, timestamp: u64) -> PriceAttestation {
+    let mut attestation = PriceAttestation {
+        price_feed_id: BTC_FEED.to_string(),
+        epoch,
+        phase,
+        price: Uint128::new(price),
+        expo: -2,
+        timestamp,
+        public_key: Binary::from(key.verifying_key().to_sec1_bytes().to_vec()),
+        signature: Binary::default(),
+    };
+    let digest = attestation_digest(&mock_env().contract.address, &attestation);
+    let signature: Signature = key.sign_prehash(&digest).unwrap();
+    attestation.signature = Binary::from(signature.to_bytes().to_vec());
+    attestation
+}
+
+fn execute_attested(deps: &mut TestDeps, time: u64, attestations: Vec<PriceAttestation>) -> Result<Response, ContractError> {
+    exec(deps, time, OPERATOR, ExecuteMsg::ExecuteRound { attestations: Some(attestations) })
+}
+
+#[test]
+fn attested_prices_lock_and_close_rounds_once_per_phase() {
+    let mut deps = setup();
+    let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+    let public_key = Binary::from(key.verifying_key().to_sec1_bytes().to_vec());
+    let err = exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources {
+        price_sources: vec![PriceSource::Attested { public_key: Binary::from(vec![2u8; 20]), scheme: SignatureScheme::P256 }],
+        max_deviation_bps: 100,
+    })
+    .unwrap_err();
+    assert!(matches!(err, ContractError::InvalidPriceSources {}));
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetPriceSources {
+        price_sources: vec![PriceSource::Attested { public_key, scheme: SignatureScheme::P256 }],
+        max_deviation_bps: 100,
+    })
+    .unwrap();
+
+    // Genesis locks on an attestation like any later round
+    start_rounds(&mut deps);
+    let genesis_lock = attest(&key, 1, PricePhase::Lock, 5_900_000, lock_time(1));
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: Some(vec![genesis_lock]) }).unwrap();
+    let round = load_round(&deps, 1);
+    assert_eq!((round.lock_price, round.lock_price_origin), (5_900_000, Some(PriceOrigin::Primary)));
+    assert!(ATTESTED_LOCK_PRICES.is_empty(&deps.storage));
+    execute_attested(&mut deps, lock_time(1), vec![]).unwrap();
+
+    let stranger = SigningKey::from_slice(&[9u8; 32]).unwrap();
+    let err = execute_attested(&mut deps, lock_time(2), vec![attest(&stranger, 2, PricePhase::Lock, 6_000_000, lock_time(2))]).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    let err = execute_attested(&mut deps, lock_time(2), vec![attest(&key, 2, PricePhase::Lock, 6_000_000, lock_time(2) + 1)]).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidAttestation {}));
+
+    let lock = attest(&key, 2, PricePhase::Lock, 6_000_000, lock_time(2));
+    execute_attested(&mut deps, lock_time(2), vec![lock.clone()]).unwrap();
+    let round = load_round(&deps, 2);
+    assert_eq!((round.lock_price, round.lock_price_origin), (6_000_000, Some(PriceOrigin::Primary)));
+    assert!(ATTESTED_LOCK_PRICES.is_empty(&deps.storage));
+
+    let err = execute_attested(&mut deps, close_time(2), vec![lock]).unwrap_err();
+    assert!(matches!(err, ContractError::AttestationReplayed {}));
+
+    // A lock signature can't be passed off as the close price
+    let mut relabelled = attest(&key, 2, PricePhase::Lock, 6_100_000, close_time(2));
+    relabelled.phase = PricePhase::Close;
+    let err = execute_attested(&mut deps, close_time(2), vec![relabelled]).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidAttestation {}));
+
+    execute_attested(&mut deps, close_time(2), vec![attest(&key, 2, PricePhase::Close, 6_100_000, close_time(2))]).unwrap();
+    let round = load_round(&deps, 2);
+    assert!(round.oracle_called);
+    assert_eq!(round.close_price, 6_100_000);
+    assert!(ATTESTED_CLOSE_PRICES.is_empty(&deps.storage));
+}
+
+#[cfg(feature = "mock-oracle")]
+#[test]
+fn a_mock_price_overrides_the_pyth_oracle() {
+    let mut deps = setup();
+    let err = exec(&mut deps, GENESIS, CAROL, ExecuteMsg::SetMockPrice { price: Uint128::new(6_000_000) }).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetMockPrice { price: Uint128::new(6_000_000) }).unwrap();
+
+    start_rounds(&mut deps);
+    exec(&mut deps, lock_time(1), OPERATOR, ExecuteMsg::GenesisLockRound { attestations: None }).unwrap();
+    let round = load_round(&deps, 1);
+    assert_eq!((round.lock_price, round.lock_price_origin), (6_000_000, Some(PriceOrigin::Mock)));
+}
+
+#[test]
+fn the_pyth_wrapper_reads_stale_prices_only_when_unchecked() {
+    let mut deps = setup();
+    set_pyth(&mut deps, &[(BTC_FEED, 6_000_000, 10, GENESIS)]);
+    let querier = deps.as_ref().querier;
+    let oracle = Addr::unchecked(PYTH);
+
+    let err = query_price(&querier, &oracle, BTC_FEED, GENESIS + 61, ORACLE_TIME_LIMIT).unwrap_err();
+    assert!(matches!(err, ContractError::OracleError(_)));
+    let price = query_price_unchecked(&querier, &oracle, BTC_FEED).unwrap();
+    assert_eq!(price, PriceInfo { price: 6_000_000, expo: -2, conf: 10, publish_time: GENESIS });
+
+    assert_eq!(query_update_fee(&querier, &oracle, &[]).unwrap(), coin(1, "uatom"));
+}
+
+#[test]
+fn cancelling_an_open_round_refunds_every_stake() {
+    let mut deps = setup();
+    start_rounds(&mut deps);
+    bet(&mut deps, ALICE, 1, Position::Bull, 100).unwrap();
+    bet(&mut deps, BOB, 1, Position::Bear, 100).unwrap();
+
+    let cancel = ExecuteMsg::CancelRound { epoch: 1, reason: "bad feed".to_string() };
+    let err = exec(&mut deps, open_time(1), CAROL, cancel.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    exec(&mut deps, GENESIS, ADMIN, ExecuteMsg::SetGuardian { guardian_address: Some(CAROL.to_string()) }).unwrap();
+    exec(&mut deps, open_time(1), CAROL, cancel.clone()).unwrap();
+    let err = exec(&mut deps, open_time(1), CAROL, cancel).unwrap_err();
+    assert!(matches!(err, ContractError::RoundCancelled { epoch: 1 }));
+    let err = bet(&mut deps, DAVE, 1, Position::Bull, 100).unwrap_err();
+    assert!(matches!(err, ContractError::RoundNotBettable {}));
+
+    // The round is skipped at lock and closes without a price
+    run_round(&mut deps, lock_time(1), 6_000_000);
+    assert_eq!(CURRENT_EPOCH.load(&deps.storage).unwrap(), 2);
+    run_round(&mut deps, close_time(1), 6_100_000);
+    for user in [ALICE, BOB] {
+        claim(&mut deps, close_time(1), user, 1).unwrap();
+        assert_eq!(balance(&deps, user), 1000);
+    }
+}
+
+#[test]
+fn cancelling_a_settled_round_drops_the_fee_and_returns_the_challenge_bond() {
+    let mut deps = setup();
+    set_challenge_window(&mut deps);
+    settle_bull_round(&mut deps);
+    challenge(&mut deps, close_time(1) + 10, CAROL, 5_900_000).unwrap();
+    assert_eq!(balance(&deps, CAROL), 950);
+
+    exec(&mut deps, close_time(1) + 20, ADMIN, ExecuteMsg::CancelRound { epoch: 1, reason: "bad close".to_string() }).unwrap();
+    assert!(!PENDING_ROUND_FEES.has(&deps.storage, 1));
+    assert_eq!(balance(&deps, CAROL), 1000);
+    assert_eq!(CHALLENGES.load(&deps.storage, 1).unwrap().upheld, Some(true));
+
+    for user in [ALICE, BOB] {
+        claim(&mut deps, close_time(1) + 20, user, 1).unwrap();
+        assert_eq!(balance(&deps, user), 1000);
+    }
+    let err = exec(&mut deps, close_time(1) + 200, ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap_err();
+    assert!(matches!(err, ContractError::NoTreasury {}));
+}
+
+#[test]
+fn a_round_whose_claims_are_open_can_no_longer_be_cancelled() {
+    let mut deps = setup();
+    settle_bull_round(&mut deps);
+
+    let err = exec(&mut deps, close_time(1), ADMIN, ExecuteMsg::CancelRound { epoch: 1, reason: "late".to_string() }).unwrap_err();
+    assert!(matches!(err, ContractError::RoundSettled { epoch: 1 }));
+    let res = exec(&mut deps, close_time(1), ADMIN, ExecuteMsg::ClaimTreasury {}).unwrap();
+    assert_eq!(attr(&res, "amount"), "6");
+}
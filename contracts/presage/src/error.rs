@@ -15,6 +15,9 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Cannot migrate from contract {contract}")]
+    InvalidMigration { contract: String },
+
     #[error("Invalid interval seconds")]
     InvalidInterval {},
 
@@ -39,12 +42,150 @@ pub enum ContractError {
     #[error("Round is not bettable")]
     RoundNotBettable {},
 
-    #[error("Already bet on this round")]
-    AlreadyBet {},
+    #[error("Already bet on the other side of this round")]
+    PositionMismatch {},
+
+    #[error("Round {epoch} does not offer this position")]
+    PositionNotOffered { epoch: u64 },
+
+    #[error("Invalid market parameters")]
+    InvalidMarket {},
+
+    #[error("Market {market_id} is closed for betting")]
+    MarketNotBettable { market_id: u64 },
+
+    #[error("Market {market_id} cannot be resolved yet")]
+    MarketNotResolvable { market_id: u64 },
+
+    #[error("Market {market_id} is already resolved")]
+    MarketAlreadyResolved { market_id: u64 },
+
+    #[error("Market {market_id} has not been resolved")]
+    MarketNotResolved { market_id: u64 },
+
+    #[error("Invalid price sources")]
+    InvalidPriceSources {},
+
+    #[error("Price sources deviate by {deviation_bps} bps, above the {max_deviation_bps} bps limit")]
+    PriceDeviation { deviation_bps: u128, max_deviation_bps: u64 },
+
+    #[error("Price published at {publish_time} is outside the window around {target}")]
+    PriceOutsideWindow { publish_time: u64, target: u64 },
+
+    #[error("Invalid price window")]
+    InvalidPriceWindow {},
+
+    #[error("Invalid circuit breaker limit")]
+    InvalidCircuitBreaker {},
+
+    #[error("Invalid price attestation")]
+    InvalidAttestation {},
+
+    #[error("Price attestation has already been used")]
+    AttestationReplayed {},
+
+    #[error("Round {epoch} is not ready to lock")]
+    RoundNotLockable { epoch: u64 },
+
+    #[error("Round {epoch} is cancelled")]
+    RoundCancelled { epoch: u64 },
+
+    #[error("Round {epoch} is already settled")]
+    RoundSettled { epoch: u64 },
+
+    #[error("Settlement challenges are disabled")]
+    ChallengesDisabled {},
+
+    #[error("Invalid settlement challenge settings")]
+    InvalidSettlementChallenge {},
+
+    #[error("Challenge window has closed for round {epoch}")]
+    ChallengeWindowClosed { epoch: u64 },
+
+    #[error("Round {epoch} has already been challenged")]
+    AlreadyChallenged { epoch: u64 },
+
+    #[error("Challenged price matches the settlement of round {epoch}")]
+    ChallengePriceUnchanged { epoch: u64 },
+
+    #[error("Challenged price lies within the confidence band of the settlement of round {epoch}")]
+    ChallengeWithinConfidence { epoch: u64 },
+
+    #[error("Round {epoch} has no open challenge")]
+    NoOpenChallenge { epoch: u64 },
+
+    #[error("Claims are not open yet for round {epoch}")]
+    ClaimsNotOpen { epoch: u64 },
+
+    #[error("Market {market_id} is not resolved this way")]
+    WrongResolution { market_id: u64 },
+
+    #[error("Voting has closed for market {market_id}")]
+    VotingClosed { market_id: u64 },
+
+    #[error("Already voted on market {market_id}")]
+    AlreadyVoted { market_id: u64 },
+
+    #[error("Market {market_id} is in its dispute window")]
+    MarketInDispute { market_id: u64 },
+
+    #[error("Market {market_id} is cancelled")]
+    MarketCancelled { market_id: u64 },
+
+    #[error("Claims are already open for market {market_id}")]
+    MarketClaimsOpen { market_id: u64 },
+
+    #[error("Market {market_id} is already disputed")]
+    MarketAlreadyDisputed { market_id: u64 },
+
+    #[error("Market {market_id} is not disputed")]
+    MarketNotDisputed { market_id: u64 },
+
+    #[error("Markets only take Yes or No positions")]
+    InvalidMarketPosition {},
+
+    #[error("No bet record found for market {market_id}")]
+    NoMarketBet { market_id: u64 },
+
+    #[error("Already claimed rewards for market {market_id}")]
+    MarketAlreadyClaimed { market_id: u64 },
+
+    #[error("Not a winner for market {market_id}")]
+    NotMarketWinner { market_id: u64 },
+
+    #[error("Invalid market kind")]
+    InvalidMarketKind {},
+
+    #[error("Invalid flat band (must be between 1 and 10000 bps)")]
+    InvalidFlatBand {},
 
     #[error("Bet amount is too small")]
     BetTooSmall {},
 
+    #[error("Bet amount is too large")]
+    BetTooLarge {},
+
+    #[error("Bet exceeds the per-user cap for this round")]
+    UserRoundCapExceeded {},
+
+    #[error("Bet exceeds the total cap for this round")]
+    RoundCapExceeded {},
+
+    #[error("Bet would push this side past the maximum pool imbalance")]
+    ImbalanceExceeded {},
+
+    #[error("Invalid exposure caps")]
+    InvalidExposureCaps {},
+
+    #[error("Bet cancellation is not enabled")]
+    CancelDisabled {},
+
+    #[error("Cancellation window has closed for epoch {epoch}")]
+    CancelWindowClosed { epoch: u64 },
+
+    #[error("Invalid cancellation fee (must be <= 1000, representing max 10%)")]
+    InvalidCancelFee {},
+
     #[error("Round has not ended for epoch {epoch}")]
     RoundNotEnded { epoch: u64 },
 
@@ -66,9 +207,36 @@ pub enum ContractError {
     #[error("Contract is already unpaused")]
     AlreadyUnpaused {},
 
+    #[error("Settlement is halted by the circuit breaker")]
+    SettlementHalted {},
+
+    #[error("Settlement is not halted")]
+    SettlementNotHalted {},
+
     #[error("No treasury funds to claim")]
     NoTreasury {},
 
+    #[error("Insufficient internal balance")]
+    InsufficientBalance {},
+
+    #[error("Invalid auto-roll fraction (must be <= 1)")]
+    InvalidRollFraction {},
+
+    #[error("Standing order must cover at least one round")]
+    InvalidStandingOrder {},
+
+    #[error("Too many active standing orders")]
+    TooManyStandingOrders {},
+
+    #[error("New rounds do not offer this position")]
+    InvalidOrderPosition {},
+
+    #[error("Standing order {id} not found")]
+    StandingOrderNotFound { id: u64 },
+
+    #[error("Operator payouts are not enabled")]
+    PushPayoutsDisabled {},
+
     #[error("Oracle error: {0}")]
     OracleError(String),
 
@@ -2,6 +2,7 @@ pub mod contract;
 pub mod error;
 pub mod msg;
 pub mod oracle;
+pub mod pyth_wrapper;
 pub mod state;
 
 pub use crate::error::ContractError;
\ No newline at end of file
@@ -1,9 +1,12 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Uint128;
-use cw20::Cw20ExecuteMsg;
+use cosmwasm_std::{Binary, Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
 
 
-use crate::state::Position;
+use crate::state::{
+    AutoRoll, BetInfo, CancelWindow, Challenge, ExposureCaps, Market, MarketKind, Position, PriceInfo, PriceOrigin, PricePhase, PriceSource,
+    RollStrategy, SettlementChallenge, StandingOrder,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -18,27 +21,109 @@ pub struct InstantiateMsg {
     pub btc_price_feed_id: String, // Pyth price feed ID for BTC/USD
 }
 
+#[cw_serde]
+pub struct MigrateMsg {}
+
 #[cw_serde]
 pub enum ExecuteMsg {
     // User actions
     BetBull { epoch: u64, amount: Uint128 },
     BetBear { epoch: u64, amount: Uint128 },
+    BetFlat { epoch: u64, amount: Uint128 },
+    BetCalm { epoch: u64, amount: Uint128 },
+    BetVolatile { epoch: u64, amount: Uint128 },
+    BetBucket { epoch: u64, amount: Uint128, bucket: u32 },
+    CancelBet { epoch: u64 },
     Claim { epochs: Vec<u64> },
+    ClaimFor { user: String, epochs: Vec<u64> },
+    BetMarket { market_id: u64, position: Position, amount: Uint128 },
+    ClaimMarkets { market_ids: Vec<u64> },
+    DisputeMarket { market_id: u64 },
+    Deposit {},
+    Receive(Cw20ReceiveMsg),
+    Withdraw { amount: Uint128 },
+    SetAutoRoll { position_strategy: RollStrategy, fraction: Decimal },
+    PlaceStandingOrder { position: Position, amount_per_round: Uint128, rounds: u64 },
+    CancelStandingOrder { id: u64 },
+    ChallengeSettlement { epoch: u64, close_price: Uint128, close_price_b: Option<Uint128>, price_update: Vec<Binary> },
     
     // Operator actions
-    ExecuteRound {},
+    ExecuteRound { attestations: Option<Vec<PriceAttestation>> },
     GenesisStartRound {},
-    GenesisLockRound {},
+    GenesisLockRound { attestations: Option<Vec<PriceAttestation>> },
+    ResolveMarket { market_id: u64 },
+    PushPayouts { epoch: u64, start_after: Option<String>, limit: Option<u32> },
+    FillStandingOrders { limit: Option<u32> }, // Repeat until `done` before the round locks
+
+    // Sent by the contract itself once a challenge's price update is on-chain
+    VerifyChallenge { epoch: u64 },
+
+    // Feeder actions
+    SubmitPrice { price_feed_id: String, price: Uint128, expo: i32 },
     
+    // Resolver actions
+    VoteMarket { market_id: u64, outcome: Position },
+
+    // Admin or proposer actions
+    CreateMarket { description: String, strike_price: Uint128, betting_deadline: u64, resolution_time: u64 },
+    CreateEventMarket {
+        description: String,
+        resolvers: Vec<String>,
+        quorum: u32,
+        betting_deadline: u64,
+        resolution_time: u64,
+        vote_window_seconds: u64,
+        dispute_window_seconds: u64,
+        dispute_bond: Uint128, // Posted by a disputing bettor; required when there is a dispute window
+    },
+
     // Admin actions
+    CancelMarket { market_id: u64 }, // Also open to anyone once a committee misses quorum
+    ResolveMarketDispute { market_id: u64, outcome: Position },
+    ResolveChallenge { epoch: u64, upheld: bool },
+    CancelRound { epoch: u64, reason: String }, // Also open to the guardian
     Pause {},
     Unpause {},
+    ResumeSettlement {},
     ClaimTreasury {},
     SetBufferAndIntervalSeconds { buffer_seconds: u64, interval_seconds: u64 },
     SetMinBetAmount { min_bet_amount: Uint128 },
     SetOperator { operator_address: String },
     SetTreasuryFee { treasury_fee: u64 },
     SetOracleInfo { oracle_address: String, btc_price_feed_id: String },
+    SetPushPayouts { enabled: bool },
+    SetProposer { proposer_address: Option<String> },
+    SetMarketKind { market_kind: MarketKind },
+    SetFlatBand { flat_band_bps: Option<u64> },
+    SetCancelWindow { cancel_window: Option<CancelWindow> },
+    SetExposureCaps { exposure_caps: ExposureCaps },
+    SetSettlementChallenge { settlement_challenge: Option<SettlementChallenge> },
+    SetPriceSources { price_sources: Vec<PriceSource>, max_deviation_bps: u64 }, // 1..=10000 bps when sources are set
+    SetFallbackPriceSource { fallback_price_source: Option<PriceSource> },
+    SetPriceWindow { price_window_seconds: u64 },
+    SetCircuitBreaker { max_price_move_bps: Option<u64> },
+    SetGuardian { guardian_address: Option<String> },
+    #[cfg(feature = "mock-oracle")]
+    SetMockPrice { price: Uint128 },
+}
+
+// Round price signed by an Attested source. The signature is over the sha256 of the
+// contract address, price_feed_id, epoch, phase, price, expo and timestamp; see attestation_digest.
+#[cw_serde]
+pub struct PriceAttestation {
+    pub price_feed_id: String,
+    pub epoch: u64,
+    pub phase: PricePhase, // Lock or close price of the round
+    pub price: Uint128,
+    pub expo: i32,
+    pub timestamp: u64,
+    pub public_key: Binary,
+    pub signature: Binary,
+}
+
+#[cw_serde]
+pub enum ReceiveMsg {
+    Deposit {},
 }
 
 #[cw_serde]
@@ -61,22 +146,55 @@ pub enum QueryMsg {
     
     #[returns(ConfigResponse)]
     GetConfig {},
+
+    #[returns(BalanceResponse)]
+    GetBalance { user: String },
+
+    #[returns(AutoRollResponse)]
+    GetAutoRoll { user: String },
+
+    #[returns(Market)]
+    GetMarket { market_id: u64 },
+
+    #[returns(MarketBetResponse)]
+    GetMarketBet { market_id: u64, user: String },
+
+    #[returns(StandingOrdersResponse)]
+    GetStandingOrders { user: String },
+
+    #[returns(ChallengeResponse)]
+    GetChallenge { epoch: u64 },
 }
 
 #[cw_serde]
 pub struct RoundResponse {
     pub epoch: u64,
+    pub kind: MarketKind,
     pub start_timestamp: u64,
     pub lock_timestamp: u64,
     pub close_timestamp: u64,
     pub lock_price: i128,
     pub close_price: i128,
+    pub lock_price_b: i128,
+    pub close_price_b: i128,
+    pub lock_price_info: Option<PriceInfo>,
+    pub close_price_info: Option<PriceInfo>,
+    pub lock_price_origin: Option<PriceOrigin>,
+    pub close_price_origin: Option<PriceOrigin>,
     pub total_amount: Uint128,
     pub bull_amount: Uint128,
     pub bear_amount: Uint128,
+    pub flat_amount: Uint128,
+    pub calm_amount: Uint128,
+    pub volatile_amount: Uint128,
+    pub flat_band_bps: Option<u64>,
+    pub bucket_amounts: Vec<Uint128>,
     pub reward_base_amount: Uint128,
     pub reward_amount: Uint128,
     pub oracle_called: bool,
+    pub claims_open_at: u64,
+    pub cancelled: bool,
+    pub flagged: bool,
 }
 
 #[cw_serde]
@@ -110,5 +228,44 @@ pub struct ConfigResponse {
     pub treasury_fee: u64,
     pub oracle_address: String,
     pub btc_price_feed_id: String,
+    pub push_payouts_enabled: bool,
+    pub proposer_address: Option<String>,
+    pub market_kind: MarketKind,
+    pub flat_band_bps: Option<u64>,
+    pub cancel_window: Option<CancelWindow>,
+    pub exposure_caps: ExposureCaps,
+    pub settlement_challenge: Option<SettlementChallenge>,
+    pub price_sources: Vec<PriceSource>,
+    pub max_deviation_bps: u64,
+    pub fallback_price_source: Option<PriceSource>,
+    pub price_window_seconds: u64,
+    pub max_price_move_bps: Option<u64>,
+    pub guardian_address: Option<String>,
     pub paused: bool,
-}
\ No newline at end of file
+    pub settlement_halted: bool,
+}
+
+#[cw_serde]
+pub struct BalanceResponse {
+    pub balance: Uint128,
+}
+
+#[cw_serde]
+pub struct AutoRollResponse {
+    pub auto_roll: Option<AutoRoll>,
+}
+
+#[cw_serde]
+pub struct StandingOrdersResponse {
+    pub orders: Vec<StandingOrder>,
+}
+
+#[cw_serde]
+pub struct MarketBetResponse {
+    pub bet: Option<BetInfo>,
+}
+
+#[cw_serde]
+pub struct ChallengeResponse {
+    pub challenge: Option<Challenge>,
+}
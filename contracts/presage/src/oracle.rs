@@ -1,47 +1,105 @@
-use cosmwasm_std::{Deps, Env};
-use pyth_sdk_cw::{query_price_feed, PriceIdentifier};
+use std::str::FromStr;
+
+use cosmwasm_std::{Addr, Api, Decimal, Deps, Env, Uint128};
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+use osmosis_std::shim::Timestamp;
+use osmosis_std::types::osmosis::twap::v1beta1::TwapQuerier;
 
 use crate::error::ContractError;
+use crate::msg::PriceAttestation;
+use crate::state::{PriceInfo, PricePhase, SignatureScheme};
 
-pub fn get_btc_price(
+pub fn get_twap_price(
     deps: Deps,
     env: Env,
-    oracle_addr: String,
-    price_feed_id: &str,
-    max_staleness: u64,
-) -> Result<i128, ContractError> {
-    let price_id = match PriceIdentifier::from_hex(price_feed_id) {
-        Ok(id) => id,
-        Err(err) => return Err(ContractError::OracleError(format!("Invalid price feed ID: {}", err))),
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+    window_seconds: u64,
+    expo: i32,
+) -> Result<PriceInfo, ContractError> {
+    let start_time = Timestamp {
+        seconds: env.block.time.seconds().saturating_sub(window_seconds) as i64,
+        nanos: 0,
     };
 
-    let oracle_addr = deps.api.addr_validate(&oracle_addr)?;
-    let price_feed_response = match query_price_feed(
-        &deps.querier,
-        oracle_addr,
-        price_id,
+    let twap_response = match TwapQuerier::new(&deps.querier).arithmetic_twap_to_now(
+        pool_id,
+        base_asset,
+        quote_asset,
+        Some(start_time),
     ) {
         Ok(res) => res,
-        Err(e) => return Err(ContractError::OracleError(format!("Error querying price feed: {}", e))),
+        Err(e) => return Err(ContractError::OracleError(format!("Error querying Osmosis TWAP: {}", e))),
     };
 
-    let price_feed = price_feed_response.price_feed;
-    
-    let current_time = env.block.time.seconds() as i64;
-    let current_price = price_feed
-        .get_price_no_older_than(current_time, max_staleness)
-        .ok_or_else(|| ContractError::OracleError("Current price is not available or too stale".to_string()))?;
+    let twap = Decimal::from_str(&twap_response.arithmetic_twap)
+        .map_err(|e| ContractError::OracleError(format!("Invalid Osmosis TWAP: {}", e)))?;
+
+    let scale = Uint128::new(10u128.pow(expo.unsigned_abs()));
+    let price = twap.checked_mul(Decimal::from_ratio(scale, 1u128))
+        .map_err(|e| ContractError::OracleError(format!("Osmosis TWAP out of range: {}", e)))?
+        .to_uint_floor();
+
+    let price = i128::try_from(price.u128()).map_err(|_| ContractError::OracleError("Osmosis TWAP out of range".to_string()))?;
+
+    Ok(PriceInfo {
+        price,
+        expo,
+        conf: 0,
+        publish_time: env.block.time.seconds(),
+    })
+}
+
+// sha256 over the contract address and price_feed_id, each prefixed with its u32 length,
+// then epoch, a phase byte (0 lock, 1 close), price (u128), expo and timestamp, all big-endian
+pub fn attestation_digest(contract_addr: &Addr, attestation: &PriceAttestation) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for field in [contract_addr.as_str(), attestation.price_feed_id.as_str()] {
+        hasher.update((field.len() as u32).to_be_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher.update(attestation.epoch.to_be_bytes());
+    hasher.update([match attestation.phase {
+        PricePhase::Lock => 0u8,
+        PricePhase::Close => 1u8,
+    }]);
+    hasher.update(attestation.price.u128().to_be_bytes());
+    hasher.update(attestation.expo.to_be_bytes());
+    hasher.update(attestation.timestamp.to_be_bytes());
+
+    hasher.finalize().into()
+}
+
+pub fn verify_attestation(
+    api: &dyn Api,
+    scheme: &SignatureScheme,
+    message_hash: &[u8; 32],
+    signature: &[u8],
+    public_key: &[u8],
+) -> Result<(), ContractError> {
+    let valid = match scheme {
+        SignatureScheme::Secp256k1 => api
+            .secp256k1_verify(message_hash, signature, public_key)
+            .map_err(|_| ContractError::InvalidAttestation {})?,
+        SignatureScheme::P256 => {
+            let verifying_key = VerifyingKey::from_sec1_bytes(public_key).map_err(|_| ContractError::InvalidAttestation {})?;
+            let signature = Signature::from_slice(signature).map_err(|_| ContractError::InvalidAttestation {})?;
+            verifying_key.verify_prehash(message_hash, &signature).is_ok()
+        }
+    };
 
-    Ok(i128::from(current_price.price))
+    if !valid {
+        return Err(ContractError::InvalidAttestation {});
+    }
+    Ok(())
 }
 
-pub fn get_mock_btc_price() -> i128 {
-    90000i128
+pub fn is_valid_public_key(scheme: &SignatureScheme, public_key: &[u8]) -> bool {
+    match scheme {
+        SignatureScheme::Secp256k1 => public_key.len() == 33 || public_key.len() == 65,
+        SignatureScheme::P256 => VerifyingKey::from_sec1_bytes(public_key).is_ok(),
+    }
 }
-pub fn get_deterministic_mock_btc_price_for_testing(is_up: bool) -> i128 {
-    let base_price = 50000i128;
-    
-    let price_change = if is_up { 1000i128 } else { -1000i128 };
-    
-    base_price + price_change
-}
\ No newline at end of file
@@ -0,0 +1,114 @@
+//! Every interaction with the Pyth contract goes through this module. The wire messages are
+//! built with this contract's cosmwasm-std, and Pyth types are converted to the contract's own
+//! here, so a pyth-sdk-cw release on another cosmwasm-std version only touches this file.
+
+use cosmwasm_std::{to_json_binary, Addr, Binary, Coin, CosmosMsg, QuerierWrapper, StdResult, Uint128, WasmMsg};
+use pyth_sdk_cw::{Price, PriceFeed, PriceIdentifier};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+use crate::state::PriceInfo;
+
+// Wire format of the Pyth contract messages used here
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum PythQueryMsg {
+    PriceFeed { id: PriceIdentifier },
+    GetUpdateFee { vaas: Vec<Binary> },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum PythExecuteMsg {
+    UpdatePriceFeeds { data: Vec<Binary> },
+}
+
+#[derive(Deserialize)]
+struct PythPriceFeedResponse {
+    price_feed: PriceFeed,
+}
+
+// Only the fields of Pyth's Coin cross over, whichever cosmwasm-std it was built with
+#[derive(Deserialize)]
+struct PythCoin {
+    denom: String,
+    amount: Uint128,
+}
+
+pub fn is_valid_feed_id(price_feed_id: &str) -> bool {
+    PriceIdentifier::from_hex(price_feed_id).is_ok()
+}
+
+fn query_price_feed(
+    querier: &QuerierWrapper,
+    oracle_address: &Addr,
+    price_feed_id: &str,
+) -> Result<PriceFeed, ContractError> {
+    let id = PriceIdentifier::from_hex(price_feed_id)
+        .map_err(|err| ContractError::OracleError(format!("Invalid price feed ID: {}", err)))?;
+
+    let response: PythPriceFeedResponse = querier
+        .query_wasm_smart(oracle_address, &PythQueryMsg::PriceFeed { id })
+        .map_err(|e| ContractError::OracleError(format!("Error querying price feed: {}", e)))?;
+
+    Ok(response.price_feed)
+}
+
+fn price_info(price: Price) -> PriceInfo {
+    PriceInfo {
+        price: i128::from(price.price),
+        expo: price.expo,
+        conf: price.conf,
+        publish_time: price.publish_time as u64,
+    }
+}
+
+pub fn query_price(
+    querier: &QuerierWrapper,
+    oracle_address: &Addr,
+    price_feed_id: &str,
+    now: u64,
+    max_staleness: u64,
+) -> Result<PriceInfo, ContractError> {
+    let price = query_price_feed(querier, oracle_address, price_feed_id)?
+        .get_price_no_older_than(now as i64, max_staleness)
+        .ok_or_else(|| ContractError::OracleError("Current price is not available or too stale".to_string()))?;
+
+    Ok(price_info(price))
+}
+
+// Latest stored price whatever its age; callers check publish_time themselves
+pub fn query_price_unchecked(
+    querier: &QuerierWrapper,
+    oracle_address: &Addr,
+    price_feed_id: &str,
+) -> Result<PriceInfo, ContractError> {
+    let price = query_price_feed(querier, oracle_address, price_feed_id)?.get_price_unchecked();
+    Ok(price_info(price))
+}
+
+// Fee the Pyth contract charges to apply `updates`
+pub fn query_update_fee(
+    querier: &QuerierWrapper,
+    oracle_address: &Addr,
+    updates: &[Binary],
+) -> Result<Coin, ContractError> {
+    let fee: PythCoin = querier
+        .query_wasm_smart(oracle_address, &PythQueryMsg::GetUpdateFee { vaas: updates.to_vec() })
+        .map_err(|e| ContractError::OracleError(format!("Error querying update fee: {}", e)))?;
+
+    Ok(Coin {
+        denom: fee.denom,
+        amount: fee.amount,
+    })
+}
+
+// Pushes signed Pyth price updates on-chain, paying the fee from query_update_fee
+pub fn update_price_feeds_msg(oracle_address: &Addr, updates: Vec<Binary>, fee: Coin) -> StdResult<CosmosMsg> {
+    let funds = if fee.amount.is_zero() { vec![] } else { vec![fee] };
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: oracle_address.to_string(),
+        msg: to_json_binary(&PythExecuteMsg::UpdatePriceFeeds { data: updates })?,
+        funds,
+    }))
+}
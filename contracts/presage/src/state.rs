@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Binary, Decimal};
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use cosmwasm_std::Uint128;
     
 
+// Fields added after the first release default when older state is read, see `migrate`
 #[cw_serde]
 pub struct Config {
     pub usdc_token: Addr,
@@ -18,29 +19,198 @@ pub struct Config {
     pub treasury_fee: u64, 
     pub oracle_address: Addr, 
     pub btc_price_feed_id: String, 
+    #[serde(default)]
+    pub push_payouts_enabled: bool,
+    #[serde(default)]
+    pub proposer_address: Option<Addr>, // May create one-off markets alongside the admin
+    #[serde(default)]
+    pub market_kind: MarketKind,    // Kind of market new rounds are started as
+    #[serde(default)]
+    pub flat_band_bps: Option<u64>, // Enables the Flat outcome for new rounds
+    #[serde(default)]
+    pub cancel_window: Option<CancelWindow>, // None disables bet cancellation
+    #[serde(default)]
+    pub exposure_caps: ExposureCaps,
+    #[serde(default)]
+    pub settlement_challenge: Option<SettlementChallenge>, // None makes settlement final at once
+    #[serde(default)]
+    pub price_sources: Vec<PriceSource>, // Empty reads Pyth at oracle_address
+    #[serde(default)]
+    pub max_deviation_bps: u64,          // Widest spread between sources before a round is cancelled
+    #[serde(default)]
+    pub fallback_price_source: Option<PriceSource>, // Tried when the sources above can't be read
+    #[serde(default = "default_price_window_seconds")]
+    pub price_window_seconds: u64,         // Max distance of a lock / close reading from its timestamp
+    #[serde(default)]
+    pub max_price_move_bps: Option<u64>,   // Circuit breaker on lock to close and round to round moves
+    #[serde(default)]
+    pub guardian_address: Option<Addr>,    // May cancel rounds alongside the admin
+}
+
+pub fn default_price_window_seconds() -> u64 {
+    60
+}
+
+#[cw_serde]
+pub enum PriceSource {
+    // Pyth contract, read for the feed id being priced
+    Pyth { oracle_address: Addr },
+    // Address pushing prices with SubmitPrice
+    Feeder { address: Addr },
+    // Osmosis TWAP over window_seconds, scaled by 10^-expo to match Pyth prices
+    OsmosisTwap { pools: Vec<TwapPool>, window_seconds: u64, expo: i32 },
+    // Round prices signed by this key and passed to ExecuteRound
+    Attested { public_key: Binary, scheme: SignatureScheme },
+}
+
+// Which of a round's two prices an attestation is for
+#[cw_serde]
+#[derive(Copy)]
+pub enum PricePhase {
+    Lock,
+    Close,
+}
+
+#[cw_serde]
+pub enum SignatureScheme {
+    Secp256k1,
+    P256,
+}
+
+#[cw_serde]
+pub struct TwapPool {
+    pub price_feed_id: String, // Feed id this pool prices
+    pub pool_id: u64,
+    pub base_asset: String,
+    pub quote_asset: String,
+}
+
+#[cw_serde]
+pub enum PriceOrigin {
+    #[cfg(feature = "mock-oracle")]
+    Mock,     // Price set with SetMockPrice
+    Primary,  // Median of the configured sources
+    Fallback, // Fallback source
+    Oracle,   // Pyth at oracle_address, no sources configured
+}
+
+// A price as read from its source; the dollar value is price * 10^expo
+#[cw_serde]
+pub struct PriceInfo {
+    pub price: i128,
+    pub expo: i32,
+    pub conf: u64, // Confidence interval, in the same units as price
+    pub publish_time: u64,
+}
+
+#[cw_serde]
+pub struct FedPrice {
+    pub price: i128,
+    pub expo: i32,
+    pub publish_time: u64,
+}
+
+#[cw_serde]
+pub struct CancelWindow {
+    pub cutoff_seconds: u64, // Cancellation closes this long before lock_timestamp
+    pub fee_bps: u64,        // Share of the stake kept by the treasury (10000 = 100%)
+}
+
+#[cw_serde]
+pub struct SettlementChallenge {
+    pub window_seconds: u64, // Claims open this long after a round ends
+    pub bond_amount: Uint128,
+}
+
+// Limits on how much can be staked; None leaves a limit off.
+#[cw_serde]
+#[derive(Default)]
+pub struct ExposureCaps {
+    pub max_bet_amount: Option<Uint128>,
+    pub max_user_round_amount: Option<Uint128>, // Per user, per round
+    pub max_round_amount: Option<Uint128>,      // Cap on a round's total_amount
+    pub max_imbalance_ratio: Option<Decimal>,   // Heavier side over lighter side
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum MarketKind {
+    // Bull / Bear, plus Flat when a band is set
+    #[default]
+    UpDown,
+    // Ascending move boundaries splitting N + 1 buckets
+    Range { boundaries_bps: Vec<i64> },
+    // Bull / Bear must clear lock_price by the offset
+    StrikeOffset { offset: StrikeOffset },
+    // Bull backs asset A outperforming asset B, Bear the reverse
+    Relative { feed_id_a: String, feed_id_b: String },
+    // Volatile wins on a move beyond threshold_bps of lock_price in either direction
+    Volatility { threshold_bps: u64 },
+}
+
+#[cw_serde]
+pub enum StrikeOffset {
+    Bps(u64),          // Basis points of the lock price
+    Absolute(Uint128), // Raw oracle price units
 }
 
 #[cw_serde]
 pub struct Round {
     pub epoch: u64,
+    #[serde(default)]
+    pub kind: MarketKind,
     pub start_timestamp: u64,
     pub lock_timestamp: u64,
     pub close_timestamp: u64,
     pub lock_price: i128,
     pub close_price: i128,
+    #[serde(default)]
+    pub lock_price_b: i128,  // Second asset of a Relative round
+    #[serde(default)]
+    pub close_price_b: i128, // Second asset of a Relative round
+    #[serde(default)]
+    pub lock_price_info: Option<PriceInfo>, // Reading behind lock_price
+    #[serde(default)]
+    pub close_price_info: Option<PriceInfo>, // Reading behind close_price
+    #[serde(default)]
+    pub lock_price_origin: Option<PriceOrigin>,
+    #[serde(default)]
+    pub close_price_origin: Option<PriceOrigin>,
     pub total_amount: Uint128,
     pub bull_amount: Uint128,
     pub bear_amount: Uint128,
+    #[serde(default)]
+    pub flat_amount: Uint128,
+    #[serde(default)]
+    pub calm_amount: Uint128,
+    #[serde(default)]
+    pub volatile_amount: Uint128,
+    #[serde(default)]
+    pub flat_band_bps: Option<u64>,
+    #[serde(default)]
+    pub bucket_amounts: Vec<Uint128>, // One pool per bucket in Range rounds
     pub reward_base_amount: Uint128,
     pub reward_amount: Uint128,
     pub oracle_called: bool,
+    #[serde(default)]
+    pub claims_open_at: u64, // End of the settlement challenge window
+    #[serde(default)]
+    pub cancelled: bool,     // Every stake is refunded
+    #[serde(default)]
+    pub flagged: bool,       // Tripped the circuit breaker and is held for review
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Position {
-    Bull, // Price goes up
-    Bear, // Price goes down
+    Bull,        // Price goes up
+    Bear,        // Price goes down
+    Flat,        // Price stays within the round's flat band
+    Bucket(u32), // Price move lands in this bucket of a Range round
+    Yes,         // One-off market resolves Yes
+    No,          // One-off market resolves No
+    Calm,        // Price moves no more than the Volatility round's threshold
+    Volatile,    // Price moves beyond the Volatility round's threshold
 }
 
 #[cw_serde]
@@ -50,10 +220,113 @@ pub struct BetInfo {
     pub claimed: bool,
 }
 
+#[cw_serde]
+pub enum RollStrategy {
+    SameSide,       // Same position as the winning bet
+    Opposite,       // Opposite of the winning bet
+    FollowMajority, // Side with the larger pool in the next round
+}
+
+#[cw_serde]
+pub struct AutoRoll {
+    pub position_strategy: RollStrategy,
+    pub fraction: Decimal, // Share of each claimed payout to reinvest
+}
+
+#[cw_serde]
+pub enum MarketResolution {
+    // Yes wins when the oracle price at resolution is above the strike
+    PriceAbove { strike_price: i128 },
+    // Yes / No is voted by the resolvers; `quorum` matching votes settle the market
+    Committee {
+        resolvers: Vec<Addr>,
+        quorum: u32,
+        vote_window_seconds: u64,    // Voting runs this long from resolution_time
+        dispute_window_seconds: u64, // Claims open this long after quorum
+        #[serde(default)]
+        dispute_bond: Uint128,       // Refunded if the admin overturns the outcome, else to the treasury
+    },
+}
+
+#[cw_serde]
+pub struct Market {
+    pub id: u64,
+    pub creator: Addr,
+    pub description: String,
+    pub resolution: MarketResolution,
+    pub betting_deadline: u64,
+    pub resolution_time: u64,
+    pub settlement_price: Option<i128>, // Oracle price for PriceAbove markets
+    pub outcome: Option<Position>,      // Yes or No once resolved
+    pub claims_open_at: Option<u64>,
+    pub cancelled: bool,                // Every stake is refunded
+    #[serde(default)]
+    pub disputed_by: Option<Addr>,      // Bettor holding claims until the admin rules
+    pub total_amount: Uint128,
+    pub yes_amount: Uint128,
+    pub no_amount: Uint128,
+    pub reward_base_amount: Uint128,
+    pub reward_amount: Uint128,
+}
+
+// Dispute of a round's close price, decided by the admin
+#[cw_serde]
+pub struct Challenge {
+    pub challenger: Addr,
+    pub bond: Uint128,
+    pub close_price: i128,
+    pub close_price_b: Option<i128>, // Second asset of a Relative round
+    pub evidence_hash: String,       // sha256 of the submitted Pyth price update, applied on-chain
+    pub upheld: Option<bool>,        // None while awaiting a decision
+}
+
+#[cw_serde]
+pub struct StandingOrder {
+    pub id: u64,
+    pub owner: Addr,
+    pub position: Position,
+    pub amount_per_round: Uint128,
+    pub rounds_remaining: u64,
+    pub escrow: Uint128, // Prepaid stake not yet placed
+}
+
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const PAUSED: Item<bool> = Item::new("paused");
+pub const SETTLEMENT_HALTED: Item<bool> = Item::new("settlement_halted"); // Set by the circuit breaker; betting and claims go on
 pub const CURRENT_EPOCH: Item<u64> = Item::new("current_epoch");
 pub const ROUNDS: Map<u64, Round> = Map::new("rounds"); 
 pub const LEDGER: Map<(u64, Addr), BetInfo> = Map::new("ledger"); 
 pub const USER_ROUNDS: Map<Addr, Vec<u64>> = Map::new("user_rounds"); 
-pub const TREASURY: Item<Uint128> = Item::new("treasury");
\ No newline at end of file
+pub const TREASURY: Item<Uint128> = Item::new("treasury");
+// Fees of settled rounds and resolved markets whose claims are not open yet; they move to
+// TREASURY once claims open, so a challenge or cancellation can still hand them back
+pub const PENDING_ROUND_FEES: Map<u64, Uint128> = Map::new("pending_round_fees");
+pub const PENDING_MARKET_FEES: Map<u64, Uint128> = Map::new("pending_market_fees");
+// Last round started by the first release, which took the fee into TREASURY at lock and only
+// ever ended the current round. Rounds up to here are never ended or charged again.
+pub const LEGACY_EPOCH: Item<u64> = Item::new("legacy_epoch");
+pub const BALANCES: Map<Addr, Uint128> = Map::new("balances");
+pub const AUTO_ROLL: Map<Addr, AutoRoll> = Map::new("auto_roll");
+pub const STANDING_ORDERS: Map<u64, StandingOrder> = Map::new("standing_orders");
+pub const OWNER_STANDING_ORDERS: Map<(Addr, u64), bool> = Map::new("owner_standing_orders"); // Order ids by owner
+pub const NEXT_ORDER_ID: Item<u64> = Item::new("next_order_id");
+pub const ORDER_FILL_CURSOR: Item<(u64, u64)> = Item::new("order_fill_cursor"); // (epoch, last filled order id)
+pub const MARKETS: Map<u64, Market> = Map::new("markets");
+pub const MARKET_LEDGER: Map<(u64, Addr), BetInfo> = Map::new("market_ledger");
+pub const MARKET_VOTES: Map<(u64, Addr), Position> = Map::new("market_votes");
+pub const FED_PRICES: Map<(String, Addr), FedPrice> = Map::new("fed_prices");
+pub type AttestedKey = (String, u64, Vec<u8>); // (price_feed_id, epoch, public_key)
+pub const ATTESTED_LOCK_PRICES: Map<AttestedKey, PriceInfo> = Map::new("attested_lock_prices");
+pub const ATTESTED_CLOSE_PRICES: Map<AttestedKey, PriceInfo> = Map::new("attested_close_prices");
+pub const USED_ATTESTATIONS: Map<Vec<u8>, bool> = Map::new("used_attestations");
+#[cfg(feature = "mock-oracle")]
+pub const MOCK_PRICE: Item<i128> = Item::new("mock_price");
+pub const CHALLENGES: Map<u64, Challenge> = Map::new("challenges");
+pub const NEXT_MARKET_ID: Item<u64> = Item::new("next_market_id");
+
+pub fn attested_prices(phase: PricePhase) -> Map<'static, AttestedKey, PriceInfo> {
+    match phase {
+        PricePhase::Lock => ATTESTED_LOCK_PRICES,
+        PricePhase::Close => ATTESTED_CLOSE_PRICES,
+    }
+}
\ No newline at end of file